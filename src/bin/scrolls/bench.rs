@@ -0,0 +1,150 @@
+use clap;
+use scrolls::enrich::{self, backend::Backend};
+use std::{path::PathBuf, time::Instant};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum BackendKind {
+    Sled,
+    #[cfg(feature = "redb")]
+    Redb,
+    #[cfg(feature = "rocksdb")]
+    RocksDb,
+}
+
+// a synthetic output roughly the size of a simple babbage output
+const VALUE_SIZE: usize = 120;
+
+fn synthetic_key(n: usize) -> String {
+    format!("{:064x}#{}", n, n % 4)
+}
+
+fn synthetic_value(n: usize) -> Vec<u8> {
+    (0..VALUE_SIZE).map(|i| ((n + i) % 256) as u8).collect()
+}
+
+fn report(backend: BackendKind, phase: &str, ops: usize, started: Instant) {
+    let elapsed = started.elapsed();
+    let rate = ops as f64 / elapsed.as_secs_f64();
+
+    println!(
+        "{:?}\t{}\t{} ops\t{:.2?}\t{:.0} ops/s",
+        backend, phase, ops, elapsed, rate
+    );
+}
+
+fn bench_backend<B: Backend>(
+    kind: BackendKind,
+    config: &B::Config,
+    args: &Args,
+) -> Result<(), scrolls::Error> {
+    let db = B::open(config)?;
+
+    let started = Instant::now();
+
+    for chunk in (0..args.utxos).collect::<Vec<_>>().chunks(args.batch_size) {
+        let items = chunk
+            .iter()
+            .map(|n| (synthetic_key(*n), synthetic_value(*n)))
+            .collect();
+
+        db.insert_batch(items)?;
+    }
+
+    report(kind, "insert", args.utxos, started);
+
+    let started = Instant::now();
+    let mut misses = 0;
+
+    // fetch in a different order than the insertion to avoid sequential reads
+    for n in (0..args.utxos)
+        .rev()
+        .step_by(2)
+        .chain((0..args.utxos).step_by(2))
+    {
+        if db.get(&synthetic_key(n))?.is_none() {
+            misses += 1;
+        }
+    }
+
+    report(kind, "fetch", args.utxos, started);
+
+    if misses > 0 {
+        log::warn!("{:?} backend missed {} utxos during fetch", kind, misses);
+    }
+
+    let started = Instant::now();
+
+    for chunk in (0..args.utxos).collect::<Vec<_>>().chunks(args.batch_size) {
+        let keys = chunk.iter().map(|n| synthetic_key(*n)).collect();
+        db.remove_batch(keys)?;
+    }
+
+    report(kind, "remove", args.utxos, started);
+
+    let started = Instant::now();
+    db.flush()?;
+    report(kind, "flush", 1, started);
+
+    Ok(())
+}
+
+pub fn run(args: &Args) -> Result<(), scrolls::Error> {
+    for kind in args.backends.iter() {
+        let db_path = args
+            .dir
+            .join(format!("{:?}", kind).to_lowercase())
+            .to_string_lossy()
+            .to_string();
+
+        match kind {
+            BackendKind::Sled => {
+                let config = enrich::sled::Config {
+                    db_path,
+                    seed: None,
+                    cache_size: None,
+                };
+                bench_backend::<enrich::sled::SledBackend>(*kind, &config, args)?
+            }
+            #[cfg(feature = "redb")]
+            BackendKind::Redb => {
+                let config = enrich::redb::Config {
+                    db_path,
+                    seed: None,
+                    cache_size: None,
+                };
+                bench_backend::<enrich::redb::RedbBackend>(*kind, &config, args)?
+            }
+            #[cfg(feature = "rocksdb")]
+            BackendKind::RocksDb => {
+                let config = enrich::rocksdb::Config {
+                    db_path,
+                    seed: None,
+                    cache_size: None,
+                };
+                bench_backend::<enrich::rocksdb::RocksDbBackend>(*kind, &config, args)?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(clap::Args)]
+#[clap(author, version, about, long_about = None)]
+pub struct Args {
+    #[clap(long, value_parser)]
+    //#[clap(description = "directory where the benchmark dbs will be created")]
+    dir: PathBuf,
+
+    #[clap(long, value_parser, value_delimiter = ',', default_value = "sled")]
+    //#[clap(description = "comma separated list of enrich backends to compare")]
+    backends: Vec<BackendKind>,
+
+    #[clap(long, value_parser, default_value_t = 100_000)]
+    //#[clap(description = "amount of synthetic utxos to process")]
+    utxos: usize,
+
+    #[clap(long, value_parser, default_value_t = 500)]
+    //#[clap(description = "amount of utxos written per batch, similar to a block")]
+    batch_size: usize,
+}
@@ -31,6 +31,8 @@ struct TuiConsole {
     enrich_removes: indicatif::ProgressBar,
     enrich_matches: indicatif::ProgressBar,
     enrich_mismatches: indicatif::ProgressBar,
+    enrich_cache_hits: indicatif::ProgressBar,
+    enrich_cache_misses: indicatif::ProgressBar,
     enrich_blocks: indicatif::ProgressBar,
 }
 
@@ -67,6 +69,8 @@ impl TuiConsole {
             enrich_removes: Self::build_counter_spinner("enrich removes", &container),
             enrich_matches: Self::build_counter_spinner("enrich matches", &container),
             enrich_mismatches: Self::build_counter_spinner("enrich mismatches", &container),
+            enrich_cache_hits: Self::build_counter_spinner("enrich cache hits", &container),
+            enrich_cache_misses: Self::build_counter_spinner("enrich cache misses", &container),
             enrich_blocks: Self::build_counter_spinner("enrich blocks", &container),
             reducer_ops_count: Self::build_counter_spinner("reducer ops", &container),
             storage_ops_count: Self::build_counter_spinner("storage ops", &container),
@@ -125,6 +129,14 @@ impl TuiConsole {
                                 self.enrich_mismatches.set_position(x);
                                 self.enrich_mismatches.set_message(state);
                             }
+                            (_, "enrich_cache_hits", Reading::Count(x)) => {
+                                self.enrich_cache_hits.set_position(x);
+                                self.enrich_cache_hits.set_message(state);
+                            }
+                            (_, "enrich_cache_misses", Reading::Count(x)) => {
+                                self.enrich_cache_misses.set_position(x);
+                                self.enrich_cache_misses.set_message(state);
+                            }
                             (_, "enrich_blocks", Reading::Count(x)) => {
                                 self.enrich_blocks.set_position(x);
                                 self.enrich_blocks.set_message(state);
@@ -179,7 +191,10 @@ impl PlainConsole {
                     log::error!("[{}] stage tether has been dropped", tether.name());
                 }
                 gasket::runtime::TetherState::Blocked(_) => {
-                    log::warn!("[{}] stage tehter is blocked or not reporting state", tether.name());
+                    log::warn!(
+                        "[{}] stage tehter is blocked or not reporting state",
+                        tether.name()
+                    );
                 }
                 gasket::runtime::TetherState::Alive(state) => {
                     log::debug!("[{}] stage is alive with state: {:?}", tether.name(), state);
@@ -1,5 +1,5 @@
 use clap;
-use scrolls::{bootstrap, crosscut, enrich, reducers, sources, storage};
+use scrolls::{bootstrap, crosscut, enrich, filter, reducers, sources, storage};
 use serde::Deserialize;
 use std::time::Duration;
 
@@ -37,6 +37,7 @@ impl From<ChainConfig> for crosscut::ChainWellKnownInfo {
 struct ConfigRoot {
     source: sources::Config,
     enrich: Option<enrich::Config>,
+    filter: Option<filter::Config>,
     reducers: Vec<reducers::Config>,
     storage: storage::Config,
     intersect: crosscut::IntersectConfig,
@@ -112,11 +113,18 @@ pub fn run(args: &Args) -> Result<(), scrolls::Error> {
 
     let enrich = config.enrich.unwrap_or_default().bootstrapper(&policy);
 
-    let reducer = reducers::Bootstrapper::new(config.reducers, &chain, &policy);
+    let filter = config
+        .filter
+        .map(|x| x.bootstrapper(&chain, &policy))
+        .transpose()?;
+
+    config.storage.check_reducers(&config.reducers)?;
+
+    let reducer = reducers::Bootstrapper::new(config.reducers, &chain, &policy)?;
 
     let storage = config.storage.plugin(&chain, &config.intersect, &policy);
 
-    let pipeline = bootstrap::build(source, enrich, reducer, storage)?;
+    let pipeline = bootstrap::build(source, enrich, filter, reducer, storage)?;
 
     log::info!("scrolls is running...");
 
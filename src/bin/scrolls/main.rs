@@ -1,6 +1,7 @@
 use clap::Parser;
 use std::process;
 
+mod bench;
 mod console;
 mod daemon;
 
@@ -10,6 +11,7 @@ mod daemon;
 #[clap(author, version, about, long_about = None)]
 enum Scrolls {
     Daemon(daemon::Args),
+    Bench(bench::Args),
 }
 
 fn main() {
@@ -17,6 +19,7 @@ fn main() {
 
     let result = match args {
         Scrolls::Daemon(x) => daemon::run(&x),
+        Scrolls::Bench(x) => bench::run(&x),
     };
 
     if let Err(err) = &result {
@@ -1,4 +1,4 @@
-use crate::{enrich, reducers, sources, storage};
+use crate::{enrich, filter, reducers, sources, storage};
 
 use gasket::{messaging::connect_ports, runtime::Tether};
 
@@ -21,6 +21,7 @@ impl Pipeline {
 pub fn build(
     mut source: sources::Bootstrapper,
     mut enrich: enrich::Bootstrapper,
+    mut filter: Option<filter::Bootstrapper>,
     mut reducer: reducers::Bootstrapper,
     mut storage: storage::Bootstrapper,
 ) -> Result<Pipeline, crate::Error> {
@@ -30,11 +31,23 @@ pub fn build(
 
     connect_ports(source.borrow_output_port(), enrich.borrow_input_port(), 100);
 
-    connect_ports(
-        enrich.borrow_output_port(),
-        reducer.borrow_input_port(),
-        100,
-    );
+    match filter.as_mut() {
+        Some(filter) => {
+            connect_ports(enrich.borrow_output_port(), filter.borrow_input_port(), 100);
+            connect_ports(
+                filter.borrow_output_port(),
+                reducer.borrow_input_port(),
+                100,
+            );
+        }
+        None => {
+            connect_ports(
+                enrich.borrow_output_port(),
+                reducer.borrow_input_port(),
+                100,
+            );
+        }
+    };
 
     connect_ports(
         reducer.borrow_output_port(),
@@ -44,6 +57,11 @@ pub fn build(
 
     source.spawn_stages(&mut pipeline, cursor);
     enrich.spawn_stages(&mut pipeline);
+
+    if let Some(filter) = filter {
+        filter.spawn_stages(&mut pipeline);
+    }
+
     reducer.spawn_stages(&mut pipeline);
     storage.spawn_stages(&mut pipeline);
 
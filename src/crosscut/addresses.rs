@@ -0,0 +1,38 @@
+use pallas::ledger::addresses::{Address, StakeAddress};
+use pallas::ledger::primitives::alonzo::StakeCredential;
+
+/// Bech32 stake address of the delegation part of an address, if it has one
+pub fn any_address_to_stake_bech32(address: Address) -> Option<String> {
+    match address {
+        Address::Shelley(s) => match StakeAddress::try_from(s).ok() {
+            Some(x) => x.to_bech32().ok(),
+            _ => None,
+        },
+        Address::Byron(_) => None,
+        Address::Stake(_) => None,
+    }
+}
+
+/// Hex of the key or script hash behind a stake credential
+pub fn stake_credential_to_string(cred: &StakeCredential) -> String {
+    match cred {
+        StakeCredential::AddrKeyhash(x) => x.to_string(),
+        StakeCredential::Scripthash(x) => x.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::any_address_to_stake_bech32;
+    use pallas::ledger::addresses::Address;
+
+    #[test]
+    fn stake_bech32() {
+        let addr = Address::from_bech32("addr1q86gknmykuldcngv0atyy56ex598p6m8f24nf9nmehmgpgfcmswqs6wnpls37lh7s3du977cxw67a9dpndnmafjs08asyqxe39").unwrap();
+        let stake_bech32 = any_address_to_stake_bech32(addr).unwrap();
+        assert_eq!(
+            stake_bech32,
+            "stake1uyudc8qgd8fslcgl0mlggk7zl0vr8d0wjksekea75eg8n7cw33m0s"
+        );
+    }
+}
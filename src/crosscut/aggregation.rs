@@ -0,0 +1,106 @@
+use pallas::ledger::traverse::MultiEraBlock;
+use serde::Deserialize;
+
+use super::{epochs::block_epoch, time::NaiveProvider, ChainWellKnownInfo};
+
+const SECONDS_PER_HOUR: u64 = 3600;
+const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+
+/// Time bucket used to split a counter into several keys
+///
+/// Keys of aggregated counters are `<key>.<bucket>`, where the bucket is the
+/// epoch number, the UTC date (`2022-09-23`), the UTC date and hour
+/// (`2022-09-23T21`) or the number of the first block of the range.
+#[derive(Deserialize, Copy, Clone, Debug, PartialEq)]
+pub enum AggrType {
+    Epoch,
+    Day,
+    Hour,
+    /// Ranges of the given number of blocks
+    BlockRange(u64),
+}
+
+/// Converts days since the unix epoch into a (year, month, day) civil date
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // shifted to start on 0000-03-01 so that leap days fall at the end of a year
+    let days = days + 719468;
+    let era = days / 146097;
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+fn format_day(timestamp: u64) -> String {
+    let (year, month, day) = civil_from_days(timestamp / SECONDS_PER_DAY);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn format_hour(timestamp: u64) -> String {
+    let hour = (timestamp % SECONDS_PER_DAY) / SECONDS_PER_HOUR;
+    format!("{}T{:02}", format_day(timestamp), hour)
+}
+
+/// Appends the bucket of a block to the keys of counting reducers
+#[derive(Clone)]
+pub struct Aggregator {
+    aggr_by: Option<AggrType>,
+    chain: ChainWellKnownInfo,
+    time: NaiveProvider,
+}
+
+impl Aggregator {
+    pub fn new(aggr_by: Option<AggrType>, chain: &ChainWellKnownInfo) -> Self {
+        Self {
+            aggr_by,
+            chain: chain.clone(),
+            time: NaiveProvider::new(chain.clone()),
+        }
+    }
+
+    /// The bucket a block falls in, `None` when no aggregation is configured
+    pub fn bucket(&self, block: &MultiEraBlock) -> Option<String> {
+        match self.aggr_by? {
+            AggrType::Epoch => Some(block_epoch(&self.chain, block).to_string()),
+            AggrType::Day => Some(format_day(self.time.slot_to_wallclock(block.slot()))),
+            AggrType::Hour => Some(format_hour(self.time.slot_to_wallclock(block.slot()))),
+            AggrType::BlockRange(size) => {
+                let size = size.max(1);
+                Some((block.number() / size * size).to_string())
+            }
+        }
+    }
+}
+
+/// Key of a counter within a bucket, or the key itself without aggregation
+pub fn bucket_key(key: String, bucket: Option<&str>) -> String {
+    match bucket {
+        Some(bucket) => format!("{}.{}", key, bucket),
+        None => key,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_formatted_as_utc() {
+        assert_eq!(format_day(0), "1970-01-01");
+        assert_eq!(format_day(951782400), "2000-02-29");
+        assert_eq!(format_day(1506203091), "2017-09-23");
+        assert_eq!(format_hour(1506203091), "2017-09-23T21");
+        assert_eq!(format_hour(1596059091), "2020-07-29T21");
+    }
+
+    #[test]
+    fn bucket_is_appended_to_key() {
+        assert_eq!(bucket_key("a.b".into(), Some("412")), "a.b.412");
+        assert_eq!(bucket_key("a.b".into(), None), "a.b");
+    }
+}
@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+use pallas::crypto::hash::Hash;
+use pallas::ledger::traverse::{Asset, MultiEraOutput};
+
+use crate::model;
+
+/// Policies whose assets are tracked, as set by a `policy_ids_hex` option
+///
+/// If specified only those policy ids as hex will be taken into account, if
+/// not all policy ids will be indexed.
+pub struct PolicyFilter(Option<Vec<Hash<28>>>);
+
+impl PolicyFilter {
+    pub fn compile(policy_ids_hex: &Option<Vec<String>>) -> Result<Self, crate::Error> {
+        let policy_ids = match policy_ids_hex {
+            Some(pids) => Some(
+                pids.iter()
+                    .map(|pid| {
+                        Hash::<28>::from_str(pid)
+                            .map_err(|_| crate::Error::config(format!("invalid policy id {}", pid)))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            None => None,
+        };
+
+        Ok(PolicyFilter(policy_ids))
+    }
+
+    pub fn accepts(&self, policy_id: &Hash<28>) -> bool {
+        match &self.0 {
+            Some(pids) => pids.contains(policy_id),
+            None => true,
+        }
+    }
+}
+
+/// Hash counter increments of the native assets held by an output, stored at
+/// `<key>.assets` with one `<policy hex>.<asset name hex>` field per asset
+pub fn asset_balance_commands(
+    key: &str,
+    txo: &MultiEraOutput,
+    sign: i64,
+    policies: &PolicyFilter,
+) -> Vec<model::CRDTCommand> {
+    let mut out = vec![];
+
+    for asset in txo.non_ada_assets() {
+        if let Asset::NativeAsset(policy_id, name, quantity) = asset {
+            if !policies.accepts(&policy_id) {
+                continue;
+            }
+
+            let field = format!("{}.{}", policy_id, hex::encode(name));
+
+            // quantities are u64 on-chain, counters can't hold the top half
+            let quantity = match i64::try_from(quantity) {
+                Ok(x) => x,
+                Err(_) => {
+                    log::warn!("skipping out of range quantity {} of {}", quantity, field);
+                    continue;
+                }
+            };
+
+            out.push(model::CRDTCommand::hash_counter(
+                None,
+                &format!("{}.assets", key),
+                field,
+                sign * quantity,
+            ));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use pallas::ledger::traverse::{Era, MultiEraOutput};
+
+    use super::{asset_balance_commands, PolicyFilter};
+    use crate::model::CRDTCommand;
+
+    /// Output holding 5 units of `aa..aa.01` and 2^63 units of `bb..bb.02`
+    const OUTPUT: &str = "82581d6111111111111111111111111111111111111111111111111111111111821a001e8480a2581caaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa1410105581cbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbba141021b8000000000000000";
+
+    fn fields(commands: Vec<CRDTCommand>) -> Vec<(String, String, i64)> {
+        commands
+            .into_iter()
+            .map(|x| match x {
+                CRDTCommand::HashCounter(key, field, delta) => (key, field, delta),
+                _ => panic!("unexpected command"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn out_of_range_quantities_are_skipped() {
+        let cbor = hex::decode(OUTPUT).unwrap();
+        let txo = MultiEraOutput::decode(Era::Alonzo, &cbor).unwrap();
+        let policies = PolicyFilter::compile(&None).unwrap();
+
+        assert_eq!(
+            fields(asset_balance_commands("k", &txo, -1, &policies)),
+            vec![("k.assets".into(), format!("{}.01", "aa".repeat(28)), -5)]
+        );
+    }
+
+    #[test]
+    fn only_listed_policies_are_kept() {
+        let cbor = hex::decode(OUTPUT).unwrap();
+        let txo = MultiEraOutput::decode(Era::Alonzo, &cbor).unwrap();
+
+        let policies = PolicyFilter::compile(&Some(vec!["cc".repeat(28)])).unwrap();
+        assert!(asset_balance_commands("k", &txo, 1, &policies).is_empty());
+
+        assert!(PolicyFilter::compile(&Some(vec!["zz".into()])).is_err());
+    }
+}
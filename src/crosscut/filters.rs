@@ -1,6 +1,17 @@
-use pallas::ledger::{
-    addresses::Address,
-    traverse::{MultiEraBlock, MultiEraTx},
+use std::str::FromStr;
+
+use bech32::FromBase32;
+use pallas::{
+    codec::utils::CborWrap,
+    crypto::hash::{Hash, Hasher},
+    ledger::{
+        addresses::{Address, ShelleyDelegationPart, ShelleyPaymentPart},
+        primitives::{
+            alonzo::{self, Metadatum},
+            babbage::{DatumOption, ScriptRef, TransactionOutput},
+        },
+        traverse::{Asset, Era, MultiEraBlock, MultiEraTx, OriginalHash},
+    },
 };
 use serde::Deserialize;
 
@@ -10,6 +21,7 @@ use crate::{crosscut, model};
 #[derive(Deserialize, Clone, Default)]
 pub struct AddressPattern {
     pub exact_hex: Option<String>,
+    /// Bech32 address, Byron base58 addresses are accepted too
     pub exact_bech32: Option<String>,
     pub payment_hex: Option<String>,
     pub payment_bech32: Option<String>,
@@ -18,56 +30,274 @@ pub struct AddressPattern {
     pub is_script: Option<bool>,
 }
 
-impl AddressPattern {
-    pub fn matches(&self, addr: Address) -> bool {
-        if let Some(x) = &self.exact_hex {
-            if addr.to_hex().eq(x) {
-                return true;
-            }
+/// Matches blocks satisfying every one of the specified conditions, `before`
+/// and `after` bounds are exclusive
+#[derive(Deserialize, Clone, Default)]
+pub struct BlockPattern {
+    pub slot_before: Option<u64>,
+    pub slot_after: Option<u64>,
+    pub number_before: Option<u64>,
+    pub number_after: Option<u64>,
+    pub epoch: Option<u64>,
+    pub epoch_before: Option<u64>,
+    pub epoch_after: Option<u64>,
+
+    /// Name of the era, case insensitive (eg: "babbage")
+    pub era: Option<String>,
+
+    /// Hex of the pool id that issued the block
+    pub issuer_hex: Option<String>,
+}
+
+/// Matches txs satisfying every one of the specified conditions, `min` and
+/// `max` bounds are inclusive
+#[derive(Deserialize, Clone, Default)]
+pub struct TransactionPattern {
+    pub is_valid: Option<bool>,
+    pub min_fee: Option<u64>,
+    pub max_fee: Option<u64>,
+
+    /// Bounds for the size in bytes of the CBOR-encoded tx
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+
+    pub has_ttl: Option<bool>,
+    pub min_ttl: Option<u64>,
+    pub max_ttl: Option<u64>,
+    pub has_validity_start: Option<bool>,
+    pub min_validity_start: Option<u64>,
+    pub max_validity_start: Option<u64>,
+    pub min_inputs: Option<u64>,
+    pub max_inputs: Option<u64>,
+    pub min_outputs: Option<u64>,
+    pub max_outputs: Option<u64>,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct AssetPattern {
+    pub policy_hex: Option<String>,
+    pub asset_name_hex: Option<String>,
+    pub min_quantity: Option<u64>,
+}
+
+/// Matches the metadatum found directly under the label, an empty pattern
+/// matches the presence of the label
+#[derive(Deserialize, Clone, Default)]
+pub struct MetadataPattern {
+    pub label: u64,
+    pub text: Option<String>,
+    pub int: Option<i64>,
+    pub bytes_hex: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CertificateKind {
+    StakeRegistration,
+    StakeDeregistration,
+    StakeDelegation,
+    PoolRegistration,
+    PoolRetirement,
+    GenesisKeyDelegation,
+    MoveInstantaneousRewards,
+}
+
+#[derive(Deserialize, Clone, Default)]
+pub struct CertificatePattern {
+    pub kind: Option<CertificateKind>,
+
+    /// Hex of the pool id for delegations, registrations and retirements
+    pub pool_hex: Option<String>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Predicate {
+    AllOf(Vec<Predicate>),
+    AnyOf(Vec<Predicate>),
+    Not(Box<Predicate>),
+    Block(BlockPattern),
+    Transaction(TransactionPattern),
+    InputAddress(AddressPattern),
+    OutputAddress(AddressPattern),
+    WithdrawalAddress(AddressPattern),
+    CollateralAddress(AddressPattern),
+
+    /// Filters by an address referenced in any part of the tx, including the
+    /// outputs read through reference inputs
+    Address(AddressPattern),
+
+    /// Filters by a native asset held in any of the tx outputs
+    OutputAsset(AssetPattern),
+
+    /// Filters by a native asset minted by the tx
+    Mint(AssetPattern),
+
+    /// Filters by a native asset burned by the tx, `min_quantity` applies to
+    /// the absolute amount being burned
+    Burn(AssetPattern),
+
+    Metadata(MetadataPattern),
+    Certificate(CertificatePattern),
+
+    /// Filters by the hex hash of a Plutus script executed by the tx, either
+    /// to spend an input, to mint or provided in the witness set
+    PlutusScript(String),
+
+    /// Filters by the hex hash of a datum attached to an output or provided
+    /// in the witness set
+    DatumHash(String),
+
+    /// Filters by the hex hash of a key listed as required signer
+    RequiredSigner(String),
+}
+
+impl Predicate {
+    pub fn and(&self, other: &Self) -> Self {
+        Predicate::AllOf(vec![self.clone(), other.clone()])
+    }
+}
+
+/// The filter option accepted by every reducer
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum ReducerFilter {
+    /// A plain list of bech32 addresses, sugar for an `any_of` of exact
+    /// address patterns (or stake patterns for `stake1...` addresses).
+    /// Reducers keyed by address only index the listed addresses.
+    Addresses(Vec<String>),
+
+    Predicate(Predicate),
+}
+
+fn address_pattern_from_bech32(value: &str) -> AddressPattern {
+    if value.starts_with("stake") {
+        AddressPattern {
+            stake_bech32: Some(value.to_owned()),
+            ..Default::default()
         }
+    } else {
+        AddressPattern {
+            exact_bech32: Some(value.to_owned()),
+            ..Default::default()
+        }
+    }
+}
 
-        if let Some(x) = &self.exact_bech32 {
-            if let Ok(addr) = addr.to_bech32() {
-                if addr.eq(x) {
-                    return true;
-                }
-            }
+impl ReducerFilter {
+    pub fn to_predicate(&self) -> Predicate {
+        match self {
+            ReducerFilter::Addresses(x) => Predicate::AnyOf(
+                x.iter()
+                    .map(|a| Predicate::Address(address_pattern_from_bech32(a)))
+                    .collect(),
+            ),
+            ReducerFilter::Predicate(x) => x.clone(),
         }
+    }
+}
 
-        if let Some(x) = &self.payment_hex {
-            if let Address::Shelley(ref a) = addr {
-                let payment_hex = a.payment().to_hex();
+fn decode_hex(value: &str) -> Result<Vec<u8>, crate::Error> {
+    hex::decode(value)
+        .map_err(|_| crate::Error::config(format!("invalid hex in filter: {}", value)))
+}
 
-                if payment_hex.eq(x) {
-                    return true;
-                }
-            }
+fn decode_hash<const B: usize>(value: &str) -> Result<Hash<B>, crate::Error> {
+    Hash::<B>::from_str(value)
+        .map_err(|_| crate::Error::config(format!("invalid hash in filter: {}", value)))
+}
+
+fn decode_bech32(value: &str) -> Result<Vec<u8>, crate::Error> {
+    let invalid = || crate::Error::config(format!("invalid bech32 in filter: {}", value));
+
+    let (_, data, _) = bech32::decode(value).map_err(|_| invalid())?;
+    Vec::<u8>::from_base32(&data).map_err(|_| invalid())
+}
+
+/// Address pattern with every value decoded into the raw bytes found on-chain
+#[derive(Clone, Default)]
+struct AddressMatcher {
+    exact: Vec<Vec<u8>>,
+    payment: Vec<Vec<u8>>,
+    stake: Vec<Vec<u8>>,
+    is_script: Option<bool>,
+}
+
+impl AddressMatcher {
+    fn compile(pattern: &AddressPattern) -> Result<Self, crate::Error> {
+        let mut out = AddressMatcher {
+            is_script: pattern.is_script,
+            ..Default::default()
+        };
+
+        if let Some(x) = &pattern.exact_hex {
+            out.exact.push(decode_hex(x)?);
         }
 
-        if let Some(x) = &self.payment_bech32 {
-            if let Address::Shelley(ref a) = addr {
-                if let Ok(payment_bech32) = a.payment().to_bech32() {
-                    if payment_bech32.eq(x) {
-                        return true;
-                    }
-                }
+        if let Some(x) = &pattern.exact_bech32 {
+            let addr = Address::from_str(x)
+                .map_err(|_| crate::Error::config(format!("invalid address in filter: {}", x)))?;
+
+            out.exact.push(addr.to_vec());
+        }
+
+        if let Some(x) = &pattern.payment_hex {
+            out.payment.push(decode_hex(x)?);
+        }
+
+        if let Some(x) = &pattern.payment_bech32 {
+            out.payment.push(decode_bech32(x)?);
+        }
+
+        if let Some(x) = &pattern.stake_hex {
+            out.stake.push(decode_hex(x)?);
+        }
+
+        if let Some(x) = &pattern.stake_bech32 {
+            let bytes = decode_bech32(x)?;
+
+            // reward addresses (stake1...) carry a header byte before the hash
+            let bytes = match bytes.len() {
+                29 => bytes[1..].to_vec(),
+                _ => bytes,
+            };
+
+            out.stake.push(bytes);
+        }
+
+        Ok(out)
+    }
+
+    fn matches(&self, addr: &Address) -> bool {
+        if !self.exact.is_empty() {
+            let bytes = addr.to_vec();
+
+            if self.exact.iter().any(|x| x.eq(&bytes)) {
+                return true;
             }
         }
 
-        if let Some(x) = &self.stake_hex {
-            if let Address::Shelley(ref a) = addr {
-                let delegation_hex = a.delegation().to_hex();
+        if let Address::Shelley(a) = addr {
+            if !self.payment.is_empty() {
+                let payment = match a.payment() {
+                    ShelleyPaymentPart::Key(h) => h.as_ref(),
+                    ShelleyPaymentPart::Script(h) => h.as_ref(),
+                };
 
-                if delegation_hex.eq(x) {
+                if self.payment.iter().any(|x| x.as_slice() == payment) {
                     return true;
                 }
             }
-        }
 
-        if let Some(x) = &self.stake_bech32 {
-            if let Address::Shelley(ref a) = addr {
-                if let Ok(deleg_bech32) = a.delegation().to_bech32() {
-                    if deleg_bech32.eq(x) {
+            if !self.stake.is_empty() {
+                let stake = match a.delegation() {
+                    ShelleyDelegationPart::Key(h) => Some(h.as_ref()),
+                    ShelleyDelegationPart::Script(h) => Some(h.as_ref()),
+                    _ => None,
+                };
+
+                if let Some(stake) = stake {
+                    if self.stake.iter().any(|x| x.as_slice() == stake) {
                         return true;
                     }
                 }
@@ -82,48 +312,315 @@ impl AddressPattern {
     }
 }
 
-#[derive(Deserialize, Clone)]
-pub struct BlockPattern {
-    pub slot_before: Option<u64>,
-    pub slot_after: Option<u64>,
+#[derive(Clone)]
+struct BlockMatcher {
+    pattern: BlockPattern,
+    era: Option<Era>,
+    issuer: Option<Hash<28>>,
 }
 
-#[derive(Deserialize, Clone)]
-pub struct TransactionPattern {
-    pub is_valid: Option<bool>,
+fn parse_era(value: &str) -> Result<Era, crate::Error> {
+    match value.to_lowercase().as_str() {
+        "byron" => Ok(Era::Byron),
+        "shelley" => Ok(Era::Shelley),
+        "allegra" => Ok(Era::Allegra),
+        "mary" => Ok(Era::Mary),
+        "alonzo" => Ok(Era::Alonzo),
+        "babbage" => Ok(Era::Babbage),
+        _ => Err(crate::Error::config(format!(
+            "unknown era in filter: {}",
+            value
+        ))),
+    }
 }
 
+impl BlockMatcher {
+    fn compile(pattern: &BlockPattern) -> Result<Self, crate::Error> {
+        Ok(BlockMatcher {
+            pattern: pattern.clone(),
+            era: pattern.era.as_deref().map(parse_era).transpose()?,
+            issuer: pattern.issuer_hex.as_deref().map(decode_hash).transpose()?,
+        })
+    }
+}
 
-#[derive(Deserialize, Clone)]
-#[serde(rename_all = "snake_case")]
-pub enum Predicate {
-    AllOf(Vec<Predicate>),
-    AnyOf(Vec<Predicate>),
-    Not(Box<Predicate>),
-    Block(BlockPattern),
+#[derive(Clone)]
+struct AssetMatcher {
+    policy: Option<Hash<28>>,
+    asset_name: Option<Vec<u8>>,
+    min_quantity: Option<u64>,
+}
+
+impl AssetMatcher {
+    fn compile(pattern: &AssetPattern) -> Result<Self, crate::Error> {
+        Ok(AssetMatcher {
+            policy: pattern.policy_hex.as_deref().map(decode_hash).transpose()?,
+            asset_name: pattern
+                .asset_name_hex
+                .as_deref()
+                .map(decode_hex)
+                .transpose()?,
+            min_quantity: pattern.min_quantity,
+        })
+    }
+
+    fn matches(&self, policy: &Hash<28>, asset_name: &[u8], quantity: u64) -> bool {
+        if let Some(x) = &self.policy {
+            if x != policy {
+                return false;
+            }
+        }
+
+        if let Some(x) = &self.asset_name {
+            if x.as_slice() != asset_name {
+                return false;
+            }
+        }
+
+        if let Some(x) = self.min_quantity {
+            if quantity < x {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(Clone)]
+struct MetadataMatcher {
+    label: u64,
+    text: Option<String>,
+    int: Option<i64>,
+    bytes: Option<Vec<u8>>,
+}
+
+impl MetadataMatcher {
+    fn compile(pattern: &MetadataPattern) -> Result<Self, crate::Error> {
+        Ok(MetadataMatcher {
+            label: pattern.label,
+            text: pattern.text.clone(),
+            int: pattern.int,
+            bytes: pattern.bytes_hex.as_deref().map(decode_hex).transpose()?,
+        })
+    }
+
+    fn matches(&self, value: &Metadatum) -> bool {
+        if let Some(x) = &self.text {
+            return matches!(value, Metadatum::Text(t) if t.eq(x));
+        }
+
+        if let Some(x) = self.int {
+            return matches!(value, Metadatum::Int(i) if i128::from(*i) == x as i128);
+        }
+
+        if let Some(x) = &self.bytes {
+            return matches!(value, Metadatum::Bytes(b) if b.as_slice() == x.as_slice());
+        }
+
+        true
+    }
+}
+
+#[derive(Clone)]
+struct CertificateMatcher {
+    kind: Option<CertificateKind>,
+    pool: Option<Hash<28>>,
+}
+
+impl CertificateMatcher {
+    fn compile(pattern: &CertificatePattern) -> Result<Self, crate::Error> {
+        Ok(CertificateMatcher {
+            kind: pattern.kind,
+            pool: pattern.pool_hex.as_deref().map(decode_hash).transpose()?,
+        })
+    }
+
+    fn matches(&self, cert: &alonzo::Certificate) -> bool {
+        let (kind, pool) = match cert {
+            alonzo::Certificate::StakeRegistration(_) => (CertificateKind::StakeRegistration, None),
+            alonzo::Certificate::StakeDeregistration(_) => {
+                (CertificateKind::StakeDeregistration, None)
+            }
+            alonzo::Certificate::StakeDelegation(_, pool) => {
+                (CertificateKind::StakeDelegation, Some(pool))
+            }
+            alonzo::Certificate::PoolRegistration { operator, .. } => {
+                (CertificateKind::PoolRegistration, Some(operator))
+            }
+            alonzo::Certificate::PoolRetirement(pool, _) => {
+                (CertificateKind::PoolRetirement, Some(pool))
+            }
+            alonzo::Certificate::GenesisKeyDelegation(..) => {
+                (CertificateKind::GenesisKeyDelegation, None)
+            }
+            alonzo::Certificate::MoveInstantaneousRewardsCert(_) => {
+                (CertificateKind::MoveInstantaneousRewards, None)
+            }
+        };
+
+        if let Some(x) = self.kind {
+            if x != kind {
+                return false;
+            }
+        }
+
+        if let Some(x) = &self.pool {
+            if pool != Some(x) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// A predicate with every configured value already decoded, built once at
+/// startup so that evaluation only needs to compare raw bytes
+#[derive(Clone)]
+enum Compiled {
+    AllOf(Vec<Compiled>),
+    AnyOf(Vec<Compiled>),
+    Not(Box<Compiled>),
+    Block(BlockMatcher),
     Transaction(TransactionPattern),
-    InputAddress(AddressPattern),
-    OutputAddress(AddressPattern),
-    WithdrawalAddress(AddressPattern),
-    CollateralAddress(AddressPattern),
+    InputAddress(AddressMatcher),
+    OutputAddress(AddressMatcher),
+    WithdrawalAddress(AddressMatcher),
+    CollateralAddress(AddressMatcher),
+    Address(AddressMatcher),
+    OutputAsset(AssetMatcher),
+    Mint(AssetMatcher),
+    Burn(AssetMatcher),
+    Metadata(MetadataMatcher),
+    Certificate(CertificateMatcher),
+    PlutusScript(Hash<28>),
+    DatumHash(Hash<32>),
+    RequiredSigner(Hash<28>),
+}
 
-    /// Filters by an address referenced in any part of the tx
-    Address(AddressPattern),
+impl Compiled {
+    fn compile(predicate: &Predicate) -> Result<Self, crate::Error> {
+        let compile_all = |items: &[Predicate]| -> Result<Vec<_>, crate::Error> {
+            items.iter().map(Compiled::compile).collect()
+        };
+
+        let out = match predicate {
+            Predicate::AllOf(x) => Compiled::AllOf(compile_all(x)?),
+            Predicate::AnyOf(x) => Compiled::AnyOf(compile_all(x)?),
+            Predicate::Not(x) => Compiled::Not(Box::new(Compiled::compile(x)?)),
+            Predicate::Block(x) => Compiled::Block(BlockMatcher::compile(x)?),
+            Predicate::Transaction(x) => Compiled::Transaction(x.clone()),
+            Predicate::InputAddress(x) => Compiled::InputAddress(AddressMatcher::compile(x)?),
+            Predicate::OutputAddress(x) => Compiled::OutputAddress(AddressMatcher::compile(x)?),
+            Predicate::WithdrawalAddress(x) => {
+                Compiled::WithdrawalAddress(AddressMatcher::compile(x)?)
+            }
+            Predicate::CollateralAddress(x) => {
+                Compiled::CollateralAddress(AddressMatcher::compile(x)?)
+            }
+            Predicate::Address(x) => Compiled::Address(AddressMatcher::compile(x)?),
+            Predicate::OutputAsset(x) => Compiled::OutputAsset(AssetMatcher::compile(x)?),
+            Predicate::Mint(x) => Compiled::Mint(AssetMatcher::compile(x)?),
+            Predicate::Burn(x) => Compiled::Burn(AssetMatcher::compile(x)?),
+            Predicate::Metadata(x) => Compiled::Metadata(MetadataMatcher::compile(x)?),
+            Predicate::Certificate(x) => Compiled::Certificate(CertificateMatcher::compile(x)?),
+            Predicate::PlutusScript(x) => Compiled::PlutusScript(decode_hash(x)?),
+            Predicate::DatumHash(x) => Compiled::DatumHash(decode_hash(x)?),
+            Predicate::RequiredSigner(x) => Compiled::RequiredSigner(decode_hash(x)?),
+        };
+
+        Ok(out)
+    }
 }
 
-impl Predicate {
-    pub fn and(&self, other: &Self) -> Self {
-        Predicate::AllOf(vec![self.clone(), other.clone()])
+/// A compiled predicate ready to be evaluated against block data
+#[derive(Clone)]
+pub struct Filter {
+    predicate: Compiled,
+    chain: crosscut::ChainWellKnownInfo,
+
+    /// Addresses that keyed reducers are restricted to, only set when the
+    /// filter was configured using the plain list form
+    scope: Option<Vec<AddressMatcher>>,
+}
+
+impl Filter {
+    pub fn compile(
+        predicate: &Predicate,
+        chain: &crosscut::ChainWellKnownInfo,
+    ) -> Result<Self, crate::Error> {
+        Ok(Filter {
+            predicate: Compiled::compile(predicate)?,
+            chain: chain.clone(),
+            scope: None,
+        })
+    }
+
+    pub fn compile_reducer_filter(
+        filter: &ReducerFilter,
+        chain: &crosscut::ChainWellKnownInfo,
+    ) -> Result<Self, crate::Error> {
+        let mut out = Filter::compile(&filter.to_predicate(), chain)?;
+
+        if let ReducerFilter::Addresses(x) = filter {
+            let scope = x
+                .iter()
+                .map(|a| AddressMatcher::compile(&address_pattern_from_bech32(a)))
+                .collect::<Result<_, _>>()?;
+
+            out.scope = Some(scope);
+        }
+
+        Ok(out)
+    }
+
+    /// Returns true if entries for this address should be indexed
+    pub fn in_scope(&self, address: &Address) -> bool {
+        match &self.scope {
+            Some(x) => x.iter().any(|m| m.matches(address)),
+            None => true,
+        }
+    }
+
+    pub fn eval(
+        &self,
+        block: &MultiEraBlock,
+        tx: &MultiEraTx,
+        ctx: &model::BlockContext,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<bool, crate::Error> {
+        eval_compiled(&self.predicate, block, tx, ctx, &self.chain, policy)
+    }
+}
+
+/// Compiles the optional filter found in a reducer config
+pub fn compile_optional(
+    filter: &Option<ReducerFilter>,
+    chain: &crosscut::ChainWellKnownInfo,
+) -> Result<Option<Filter>, crate::Error> {
+    filter
+        .as_ref()
+        .map(|x| Filter::compile_reducer_filter(x, chain))
+        .transpose()
+}
+
+/// Returns true if a reducer with the given filter should index entries for
+/// the address
+pub fn in_scope(filter: &Option<Filter>, address: &Address) -> bool {
+    match filter {
+        Some(x) => x.in_scope(address),
+        None => true,
     }
 }
 
 #[inline]
-fn eval_output_address(tx: &MultiEraTx, pattern: &AddressPattern) -> Result<bool, crate::Error> {
+fn eval_output_address(tx: &MultiEraTx, pattern: &AddressMatcher) -> Result<bool, crate::Error> {
     let x = tx
         .outputs()
         .iter()
         .filter_map(|o| o.address().ok())
-        .any(|a| pattern.matches(a));
+        .any(|a| pattern.matches(&a));
 
     Ok(x)
 }
@@ -132,14 +629,14 @@ fn eval_output_address(tx: &MultiEraTx, pattern: &AddressPattern) -> Result<bool
 fn eval_input_address(
     tx: &MultiEraTx,
     ctx: &model::BlockContext,
-    pattern: &AddressPattern,
+    pattern: &AddressMatcher,
     policy: &crosscut::policies::RuntimePolicy,
 ) -> Result<bool, crate::Error> {
     for input in tx.inputs() {
         let utxo = ctx.find_utxo(&input.output_ref()).apply_policy(policy)?;
         if let Some(utxo) = utxo {
             if let Some(addr) = utxo.address().ok() {
-                if pattern.matches(addr) {
+                if pattern.matches(&addr) {
                     return Ok(true);
                 }
             }
@@ -153,14 +650,14 @@ fn eval_input_address(
 fn eval_collateral_address(
     tx: &MultiEraTx,
     ctx: &model::BlockContext,
-    pattern: &AddressPattern,
+    pattern: &AddressMatcher,
     policy: &crosscut::policies::RuntimePolicy,
 ) -> Result<bool, crate::Error> {
     for input in tx.collateral() {
         let utxo = ctx.find_utxo(&input.output_ref()).apply_policy(policy)?;
         if let Some(utxo) = utxo {
             if let Some(addr) = utxo.address().ok() {
-                if pattern.matches(addr) {
+                if pattern.matches(&addr) {
                     return Ok(true);
                 }
             }
@@ -173,14 +670,30 @@ fn eval_collateral_address(
 #[inline]
 fn eval_withdrawal_address(
     tx: &MultiEraTx,
-    pattern: &AddressPattern,
+    pattern: &AddressMatcher,
 ) -> Result<bool, crate::Error> {
     let x = tx
         .withdrawals()
         .collect::<Vec<_>>()
         .iter()
         .filter_map(|(b, _)| Address::from_bytes(b).ok())
-        .any(|a| pattern.matches(a));
+        .any(|a| pattern.matches(&a));
+
+    Ok(x)
+}
+
+#[inline]
+fn eval_reference_address(
+    tx: &MultiEraTx,
+    ctx: &model::BlockContext,
+    pattern: &AddressMatcher,
+    policy: &crosscut::policies::RuntimePolicy,
+) -> Result<bool, crate::Error> {
+    let x = ctx
+        .find_reference_txos(tx, policy)?
+        .iter()
+        .filter_map(|(_, utxo)| utxo.address().ok())
+        .any(|a| pattern.matches(&a));
 
     Ok(x)
 }
@@ -188,7 +701,7 @@ fn eval_withdrawal_address(
 fn eval_address(
     tx: &MultiEraTx,
     ctx: &model::BlockContext,
-    pattern: &AddressPattern,
+    pattern: &AddressMatcher,
     policy: &crosscut::policies::RuntimePolicy,
 ) -> Result<bool, crate::Error> {
     if eval_output_address(tx, pattern)? {
@@ -207,39 +720,295 @@ fn eval_address(
         return Ok(true);
     }
 
+    if eval_reference_address(tx, ctx, pattern, policy)? {
+        return Ok(true);
+    }
+
     Ok(false)
 }
 
-fn eval_block(block: &MultiEraBlock, pattern: &BlockPattern) -> Result<bool, crate::Error> {
-    if let Some(x) = pattern.slot_after {
-        return Ok(block.slot() > x);
+#[inline]
+fn eval_output_asset(tx: &MultiEraTx, pattern: &AssetMatcher) -> Result<bool, crate::Error> {
+    let x = tx
+        .outputs()
+        .iter()
+        .flat_map(|o| o.non_ada_assets())
+        .any(|asset| match asset {
+            Asset::NativeAsset(policy, name, quantity) => pattern.matches(&policy, &name, quantity),
+            _ => false,
+        });
+
+    Ok(x)
+}
+
+#[inline]
+fn eval_mint(tx: &MultiEraTx, pattern: &AssetMatcher, burn: bool) -> Result<bool, crate::Error> {
+    if let Some(mint) = tx.mint().as_alonzo() {
+        for (policy, assets) in mint.iter() {
+            for (name, quantity) in assets.iter() {
+                let matches = match (burn, *quantity) {
+                    (false, q) if q > 0 => pattern.matches(policy, name, q as u64),
+                    (true, q) if q < 0 => pattern.matches(policy, name, q.unsigned_abs()),
+                    _ => false,
+                };
+
+                if matches {
+                    return Ok(true);
+                }
+            }
+        }
     }
 
-    if let Some(x) = pattern.slot_before {
-        return Ok(block.slot() < x);
+    Ok(false)
+}
+
+#[inline]
+fn eval_metadata(tx: &MultiEraTx, pattern: &MetadataMatcher) -> Result<bool, crate::Error> {
+    if let Some(metadata) = tx.metadata().as_alonzo() {
+        for (label, value) in metadata.iter() {
+            if *label == pattern.label && pattern.matches(value) {
+                return Ok(true);
+            }
+        }
     }
 
     Ok(false)
 }
 
+#[inline]
+fn eval_certificate(tx: &MultiEraTx, pattern: &CertificateMatcher) -> Result<bool, crate::Error> {
+    let x = tx
+        .certs()
+        .iter()
+        .filter_map(|c| c.as_alonzo())
+        .any(|c| pattern.matches(c));
+
+    Ok(x)
+}
+
+fn eval_plutus_script(
+    tx: &MultiEraTx,
+    ctx: &model::BlockContext,
+    expected: &Hash<28>,
+    policy: &crosscut::policies::RuntimePolicy,
+) -> Result<bool, crate::Error> {
+    for script in tx.plutus_v1_scripts() {
+        if Hasher::<224>::hash_tagged(&script.0, 1).eq(expected) {
+            return Ok(true);
+        }
+    }
+
+    for script in tx.plutus_v2_scripts() {
+        if Hasher::<224>::hash_tagged(&script.0, 2).eq(expected) {
+            return Ok(true);
+        }
+    }
+
+    if let Some(mint) = tx.mint().as_alonzo() {
+        if mint.iter().any(|(pid, _)| pid.eq(expected)) {
+            return Ok(true);
+        }
+    }
+
+    // scripts provided through reference inputs only show up as the payment
+    // part of the spent outputs
+    for (_, utxo) in ctx.find_consumed_txos(tx, policy)? {
+        if let Ok(Address::Shelley(addr)) = utxo.address() {
+            if let ShelleyPaymentPart::Script(hash) = addr.payment() {
+                if hash.eq(expected) {
+                    return Ok(true);
+                }
+            }
+        }
+    }
+
+    // and as the script ref of the outputs read by the tx
+    for (_, utxo) in ctx.find_reference_txos(tx, policy)? {
+        if let Some(TransactionOutput::PostAlonzo(x)) = utxo.as_babbage() {
+            let hash = match &x.script_ref {
+                Some(CborWrap(ScriptRef::PlutusV1Script(s))) => Hasher::<224>::hash_tagged(&s.0, 1),
+                Some(CborWrap(ScriptRef::PlutusV2Script(s))) => Hasher::<224>::hash_tagged(&s.0, 2),
+                _ => continue,
+            };
+
+            if hash.eq(expected) {
+                return Ok(true);
+            }
+        }
+    }
+
+    Ok(false)
+}
+
+#[inline]
+fn eval_datum_hash(tx: &MultiEraTx, expected: &Hash<32>) -> Result<bool, crate::Error> {
+    for output in tx.outputs() {
+        if let Some(DatumOption::Hash(hash)) = output.datum() {
+            if hash.eq(expected) {
+                return Ok(true);
+            }
+        }
+    }
+
+    let x = tx
+        .plutus_data()
+        .iter()
+        .any(|d| d.original_hash().eq(expected));
+
+    Ok(x)
+}
+
+#[inline]
+fn eval_required_signer(tx: &MultiEraTx, expected: &Hash<28>) -> Result<bool, crate::Error> {
+    let x = match tx.required_signers() {
+        Some(signers) => signers.iter().any(|s| s.eq(expected)),
+        None => false,
+    };
+
+    Ok(x)
+}
+
+#[inline]
+fn is_before(value: u64, bound: Option<u64>) -> bool {
+    bound.map(|x| value < x).unwrap_or(true)
+}
+
+#[inline]
+fn is_after(value: u64, bound: Option<u64>) -> bool {
+    bound.map(|x| value > x).unwrap_or(true)
+}
+
+#[inline]
+fn is_within(value: Option<u64>, min: Option<u64>, max: Option<u64>) -> bool {
+    if min.is_none() && max.is_none() {
+        return true;
+    }
+
+    match value {
+        Some(v) => min.map(|x| v >= x).unwrap_or(true) && max.map(|x| v <= x).unwrap_or(true),
+        // a missing value can't satisfy any bound
+        None => false,
+    }
+}
+
+fn eval_block(
+    block: &MultiEraBlock,
+    matcher: &BlockMatcher,
+    chain: &crosscut::ChainWellKnownInfo,
+) -> Result<bool, crate::Error> {
+    let pattern = &matcher.pattern;
+
+    if !is_after(block.slot(), pattern.slot_after) || !is_before(block.slot(), pattern.slot_before)
+    {
+        return Ok(false);
+    }
+
+    let number = block.number();
+
+    if !is_after(number, pattern.number_after) || !is_before(number, pattern.number_before) {
+        return Ok(false);
+    }
+
+    if pattern.epoch.is_some() || pattern.epoch_after.is_some() || pattern.epoch_before.is_some() {
+        let epoch = crosscut::epochs::block_epoch(chain, block);
+
+        if pattern.epoch.map(|x| x != epoch).unwrap_or(false) {
+            return Ok(false);
+        }
+
+        if !is_after(epoch, pattern.epoch_after) || !is_before(epoch, pattern.epoch_before) {
+            return Ok(false);
+        }
+    }
+
+    if let Some(x) = &matcher.era {
+        if block.era() != *x {
+            return Ok(false);
+        }
+    }
+
+    if let Some(x) = &matcher.issuer {
+        let issuer = block.header().issuer_vkey().map(Hasher::<224>::hash);
+
+        if issuer.as_ref() != Some(x) {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
 fn eval_transaction(tx: &MultiEraTx, pattern: &TransactionPattern) -> Result<bool, crate::Error> {
     if let Some(b) = pattern.is_valid {
-        return Ok(tx.is_valid() == b)
+        if tx.is_valid() != b {
+            return Ok(false);
+        }
     }
 
-    Ok(false)
+    if !is_within(tx.fee(), pattern.min_fee, pattern.max_fee) {
+        return Ok(false);
+    }
+
+    if pattern.min_size.is_some() || pattern.max_size.is_some() {
+        let size = tx.encode().len() as u64;
+
+        if !is_within(Some(size), pattern.min_size, pattern.max_size) {
+            return Ok(false);
+        }
+    }
+
+    if let Some(b) = pattern.has_ttl {
+        if tx.ttl().is_some() != b {
+            return Ok(false);
+        }
+    }
+
+    if !is_within(tx.ttl(), pattern.min_ttl, pattern.max_ttl) {
+        return Ok(false);
+    }
+
+    if let Some(b) = pattern.has_validity_start {
+        if tx.validity_start().is_some() != b {
+            return Ok(false);
+        }
+    }
+
+    let validity_start = tx.validity_start();
+
+    if !is_within(
+        validity_start,
+        pattern.min_validity_start,
+        pattern.max_validity_start,
+    ) {
+        return Ok(false);
+    }
+
+    let inputs = tx.inputs().len() as u64;
+
+    if !is_within(Some(inputs), pattern.min_inputs, pattern.max_inputs) {
+        return Ok(false);
+    }
+
+    let outputs = tx.outputs().len() as u64;
+
+    if !is_within(Some(outputs), pattern.min_outputs, pattern.max_outputs) {
+        return Ok(false);
+    }
+
+    Ok(true)
 }
 
 #[inline]
 fn eval_any_of(
-    predicates: &[Predicate],
+    predicates: &[Compiled],
     block: &MultiEraBlock,
     tx: &MultiEraTx,
     ctx: &model::BlockContext,
+    chain: &crosscut::ChainWellKnownInfo,
     policy: &crosscut::policies::RuntimePolicy,
 ) -> Result<bool, crate::Error> {
     for p in predicates.iter() {
-        if eval_predicate(p, block, tx, ctx, policy)? {
+        if eval_compiled(p, block, tx, ctx, chain, policy)? {
             return Ok(true);
         }
     }
@@ -249,14 +1018,15 @@ fn eval_any_of(
 
 #[inline]
 fn eval_all_of(
-    predicates: &[Predicate],
+    predicates: &[Compiled],
     block: &MultiEraBlock,
     tx: &MultiEraTx,
     ctx: &model::BlockContext,
+    chain: &crosscut::ChainWellKnownInfo,
     policy: &crosscut::policies::RuntimePolicy,
 ) -> Result<bool, crate::Error> {
     for p in predicates.iter() {
-        if !eval_predicate(p, block, tx, ctx, policy)? {
+        if !eval_compiled(p, block, tx, ctx, chain, policy)? {
             return Ok(false);
         }
     }
@@ -264,24 +1034,33 @@ fn eval_all_of(
     Ok(true)
 }
 
-pub fn eval_predicate(
-    predicate: &Predicate,
+fn eval_compiled(
+    predicate: &Compiled,
     block: &MultiEraBlock,
     tx: &MultiEraTx,
     ctx: &model::BlockContext,
+    chain: &crosscut::ChainWellKnownInfo,
     policy: &crosscut::policies::RuntimePolicy,
 ) -> Result<bool, crate::Error> {
     match predicate {
-        Predicate::Not(x) => eval_predicate(x, block, tx, ctx, policy).map(|x| !x),
-        Predicate::AnyOf(x) => eval_any_of(x, block, tx, ctx, policy),
-        Predicate::AllOf(x) => eval_all_of(x, block, tx, ctx, policy),
-        Predicate::OutputAddress(x) => eval_output_address(tx, x),
-        Predicate::InputAddress(x) => eval_input_address(tx, ctx, x, policy),
-        Predicate::WithdrawalAddress(x) => eval_withdrawal_address(tx, x),
-        Predicate::CollateralAddress(x) => eval_collateral_address(tx, ctx, x, policy),
-        Predicate::Address(x) => eval_address(tx, ctx, x, policy),
-        Predicate::Block(x) => eval_block(block, x),
-        Predicate::Transaction(x) => eval_transaction(tx, x),
+        Compiled::Not(x) => eval_compiled(x, block, tx, ctx, chain, policy).map(|x| !x),
+        Compiled::AnyOf(x) => eval_any_of(x, block, tx, ctx, chain, policy),
+        Compiled::AllOf(x) => eval_all_of(x, block, tx, ctx, chain, policy),
+        Compiled::OutputAddress(x) => eval_output_address(tx, x),
+        Compiled::InputAddress(x) => eval_input_address(tx, ctx, x, policy),
+        Compiled::WithdrawalAddress(x) => eval_withdrawal_address(tx, x),
+        Compiled::CollateralAddress(x) => eval_collateral_address(tx, ctx, x, policy),
+        Compiled::Address(x) => eval_address(tx, ctx, x, policy),
+        Compiled::Block(x) => eval_block(block, x, chain),
+        Compiled::Transaction(x) => eval_transaction(tx, x),
+        Compiled::OutputAsset(x) => eval_output_asset(tx, x),
+        Compiled::Mint(x) => eval_mint(tx, x, false),
+        Compiled::Burn(x) => eval_mint(tx, x, true),
+        Compiled::Metadata(x) => eval_metadata(tx, x),
+        Compiled::Certificate(x) => eval_certificate(tx, x),
+        Compiled::PlutusScript(x) => eval_plutus_script(tx, ctx, x, policy),
+        Compiled::DatumHash(x) => eval_datum_hash(tx, x),
+        Compiled::RequiredSigner(x) => eval_required_signer(tx, x),
     }
 }
 
@@ -290,27 +1069,36 @@ mod tests {
     use pallas::ledger::traverse::MultiEraBlock;
 
     use crate::{
-        crosscut::policies::{ErrorAction, RuntimePolicy},
+        crosscut::{
+            policies::{ErrorAction, RuntimePolicy},
+            ChainWellKnownInfo,
+        },
         model::BlockContext,
     };
 
-    use super::{eval_predicate, AddressPattern, Predicate};
+    use super::{
+        AddressPattern, BlockPattern, CertificateKind, Filter, Predicate, ReducerFilter,
+        TransactionPattern,
+    };
 
     fn test_predicate_in_block(predicate: &Predicate, expected_txs: &[usize]) {
         let cbor = include_str!("../../assets/test.block");
         let bytes = hex::decode(cbor).unwrap();
         let block = MultiEraBlock::decode(&bytes).unwrap();
         let ctx = BlockContext::default();
+        let chain = ChainWellKnownInfo::mainnet();
         let policy = RuntimePolicy {
             missing_data: Some(ErrorAction::Skip),
             ..Default::default()
         };
 
+        let filter = Filter::compile(predicate, &chain).unwrap();
+
         let idxs: Vec<_> = block
             .txs()
             .iter()
             .enumerate()
-            .filter(|(_, tx)| eval_predicate(predicate, &block, tx, &ctx, &policy).unwrap())
+            .filter(|(_, tx)| filter.eval(&block, tx, &ctx, &policy).unwrap())
             .map(|(idx, _)| idx)
             .collect();
 
@@ -363,4 +1151,121 @@ mod tests {
 
         test_predicate_in_block(&x, &[0]);
     }
+
+    #[test]
+    fn certificate_kind_from_config() {
+        let x: Predicate = serde_json::from_str(
+            r#"{ "certificate": { "kind": "stake_delegation", "pool_hex": "abcd" } }"#,
+        )
+        .unwrap();
+
+        match x {
+            Predicate::Certificate(x) => {
+                assert!(x.kind == Some(CertificateKind::StakeDelegation));
+                assert_eq!(x.pool_hex.as_deref(), Some("abcd"));
+            }
+            _ => panic!("unexpected predicate"),
+        }
+    }
+
+    #[test]
+    fn block_slot_range_requires_both_bounds() {
+        let cbor = include_str!("../../assets/test.block");
+        let bytes = hex::decode(cbor).unwrap();
+        let block = MultiEraBlock::decode(&bytes).unwrap();
+        let txs: Vec<_> = (0..block.txs().len()).collect();
+
+        let inside = Predicate::Block(BlockPattern {
+            slot_after: Some(block.slot() - 1),
+            slot_before: Some(block.slot() + 1),
+            ..Default::default()
+        });
+
+        test_predicate_in_block(&inside, &txs);
+
+        let outside = Predicate::Block(BlockPattern {
+            slot_after: Some(block.slot() - 1),
+            slot_before: Some(block.slot()),
+            ..Default::default()
+        });
+
+        test_predicate_in_block(&outside, &[]);
+    }
+
+    #[test]
+    fn transaction_bounds_are_combined() {
+        let any_valid = Predicate::Transaction(TransactionPattern {
+            is_valid: Some(true),
+            min_inputs: Some(1),
+            ..Default::default()
+        });
+
+        let cbor = include_str!("../../assets/test.block");
+        let bytes = hex::decode(cbor).unwrap();
+        let block = MultiEraBlock::decode(&bytes).unwrap();
+
+        let expected: Vec<_> = block
+            .txs()
+            .iter()
+            .enumerate()
+            .filter(|(_, tx)| tx.is_valid() && !tx.inputs().is_empty())
+            .map(|(idx, _)| idx)
+            .collect();
+
+        test_predicate_in_block(&any_valid, &expected);
+
+        let impossible = Predicate::Transaction(TransactionPattern {
+            min_outputs: Some(2),
+            max_outputs: Some(1),
+            ..Default::default()
+        });
+
+        test_predicate_in_block(&impossible, &[]);
+    }
+
+    #[test]
+    fn invalid_patterns_fail_to_compile() {
+        let chain = ChainWellKnownInfo::mainnet();
+
+        let x = Predicate::OutputAddress(AddressPattern {
+            exact_bech32: Some("addr1notreallyanaddress".into()),
+            ..Default::default()
+        });
+
+        assert!(Filter::compile(&x, &chain).is_err());
+
+        let x = Predicate::Block(BlockPattern {
+            era: Some("goguen".into()),
+            ..Default::default()
+        });
+
+        assert!(Filter::compile(&x, &chain).is_err());
+
+        let x = Predicate::RequiredSigner("zz".into());
+
+        assert!(Filter::compile(&x, &chain).is_err());
+    }
+
+    #[test]
+    fn address_list_is_sugar_for_any_of() {
+        let x: ReducerFilter = serde_json::from_str(
+            r#"["addr1q8fukvydr8m5y3gztte3d4tnw0v5myvshusmu45phf20h395kqnygcykgjy42m29tksmwnd0js0z8p3swm5ntryhfu8sg7835c"]"#,
+        )
+        .unwrap();
+
+        test_predicate_in_block(&x.to_predicate(), &[0]);
+
+        // byron addresses are listed in base58
+        let x: ReducerFilter = serde_json::from_str(
+            r#"["DdzFFzCqrht8M2mAmGGpXHosnaykrXsRomuy9a8DsdcN8kK5PMrvByoyPjcLLYvoPb6mpTauha3u6ryVBzNgp8AwzdDZ1wEhfoZZvyEE"]"#,
+        )
+        .unwrap();
+
+        test_predicate_in_block(&x.to_predicate(), &[56]);
+
+        let x: ReducerFilter =
+            serde_json::from_str(r#"{ "output_address": { "is_script": true } }"#).unwrap();
+
+        assert!(matches!(x, ReducerFilter::Predicate(_)));
+    }
 }
@@ -0,0 +1,85 @@
+use pallas::ledger::primitives::alonzo::Metadatum;
+use serde_json::{json, Value};
+
+/// Renders an int as a json number, or as text when out of range
+pub fn int_to_json(x: i128) -> Value {
+    if let Ok(x) = i64::try_from(x) {
+        return json!(x);
+    }
+
+    if let Ok(x) = u64::try_from(x) {
+        return json!(x);
+    }
+
+    // out of range for json numbers, keep the exact value as text
+    json!(x.to_string())
+}
+
+fn metadatum_to_key(key: &Metadatum) -> String {
+    match key {
+        Metadatum::Text(x) => x.clone(),
+        Metadatum::Int(x) => i128::from(*x).to_string(),
+        Metadatum::Bytes(x) => format!("0x{}", hex::encode(x.as_slice())),
+        other => metadatum_to_json(other).to_string(),
+    }
+}
+
+/// Renders a metadatum using the "no schema" json mapping of cardano-cli
+///
+/// Bytes are encoded as `0x` prefixed hex strings and non-text map keys are
+/// converted to their string representation.
+pub fn metadatum_to_json(value: &Metadatum) -> Value {
+    match value {
+        Metadatum::Int(x) => int_to_json(i128::from(*x)),
+        Metadatum::Bytes(x) => json!(format!("0x{}", hex::encode(x.as_slice()))),
+        Metadatum::Text(x) => json!(x),
+        Metadatum::Array(x) => Value::Array(x.iter().map(metadatum_to_json).collect()),
+        Metadatum::Map(x) => Value::Object(
+            x.iter()
+                .map(|(k, v)| (metadatum_to_key(k), metadatum_to_json(v)))
+                .collect(),
+        ),
+    }
+}
+
+/// Selects a nested value using a dot-separated path (eg: `msg.0`)
+///
+/// Numeric segments index into arrays, any other segment is used as an object
+/// key. An empty path selects the whole value.
+pub fn select_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .filter(|x| !x.is_empty())
+        .try_fold(value, |current, segment| match current {
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            Value::Object(map) => map.get(segment),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::codec::utils::KeyValuePairs;
+    use pallas::ledger::primitives::alonzo::Metadatum;
+    use serde_json::json;
+
+    use super::{metadatum_to_json, select_path};
+
+    #[test]
+    fn cip20_message_is_selected() {
+        let value = Metadatum::Map(KeyValuePairs::from(vec![(
+            Metadatum::Text("msg".into()),
+            Metadatum::Array(vec![
+                Metadatum::Text("hello".into()),
+                Metadatum::Text("world".into()),
+            ]),
+        )]));
+
+        let json = metadatum_to_json(&value);
+        assert_eq!(json, json!({ "msg": ["hello", "world"] }));
+
+        assert_eq!(select_path(&json, "msg.1"), Some(&json!("world")));
+        assert_eq!(select_path(&json, ""), Some(&json));
+        assert_eq!(select_path(&json, "msg.2"), None);
+        assert_eq!(select_path(&json, "other"), None);
+    }
+}
@@ -1,6 +1,11 @@
+pub mod addresses;
+pub mod aggregation;
+pub mod assets;
 mod args;
 pub mod epochs;
 pub mod filters;
+pub mod metadata;
+pub mod outputs;
 pub mod policies;
 pub mod time;
 
@@ -0,0 +1,155 @@
+use pallas::codec::minicbor::{self, data::Type, Decoder};
+use pallas::ledger::traverse::MultiEraTx;
+
+type DecodeResult<T> = Result<T, minicbor::decode::Error>;
+
+/// Original bytes of the parts of an output that are hashed or stored as-is
+///
+/// Decoded values re-encode to canonical cbor, which isn't necessarily what
+/// the tx author submitted, so these are sliced out of the tx body instead.
+#[derive(Default, Debug, PartialEq)]
+pub struct OutputParts<'a> {
+    /// Bytes wrapped by the tag-24 of an inline datum
+    pub inline_datum: Option<&'a [u8]>,
+
+    /// Bytes wrapped by the tag-24 of a script ref (`[kind, script]`)
+    pub script_ref: Option<&'a [u8]>,
+}
+
+fn has_next(d: &mut Decoder, len: Option<u64>, idx: u64) -> DecodeResult<bool> {
+    match len {
+        Some(n) => Ok(idx < n),
+        None => Ok(d.datatype()? != Type::Break),
+    }
+}
+
+/// Skips the next item, returning its bytes
+fn next_item<'a>(d: &mut Decoder<'a>, bytes: &'a [u8]) -> DecodeResult<&'a [u8]> {
+    let start = d.position();
+    d.skip()?;
+    Ok(&bytes[start..d.position()])
+}
+
+fn body_outputs(body: &[u8]) -> DecodeResult<Vec<&[u8]>> {
+    let mut d = Decoder::new(body);
+    let len = d.map()?;
+    let mut idx = 0;
+
+    while has_next(&mut d, len, idx)? {
+        idx += 1;
+
+        if d.u32()? != 1 {
+            d.skip()?;
+            continue;
+        }
+
+        let len = d.array()?;
+        let mut outputs = vec![];
+
+        while has_next(&mut d, len, outputs.len() as u64)? {
+            outputs.push(next_item(&mut d, body)?);
+        }
+
+        return Ok(outputs);
+    }
+
+    Ok(vec![])
+}
+
+/// Original bytes of each output of a Babbage tx, in order
+///
+/// `None` for txs of previous eras, their outputs can't hold inline datums
+/// nor script refs.
+pub fn babbage_output_bytes<'a>(tx: &'a MultiEraTx) -> Option<Vec<&'a [u8]>> {
+    let body = tx.as_babbage()?.transaction_body.raw_cbor();
+    body_outputs(body).ok()
+}
+
+/// Slices the inline datum and script ref out of the bytes of an output
+pub fn output_parts(output: &[u8]) -> DecodeResult<OutputParts> {
+    let mut d = Decoder::new(output);
+    let mut parts = OutputParts::default();
+
+    // legacy outputs are arrays, only post-alonzo maps have these fields
+    if !matches!(d.datatype()?, Type::Map | Type::MapIndef) {
+        return Ok(parts);
+    }
+
+    let len = d.map()?;
+    let mut idx = 0;
+
+    while has_next(&mut d, len, idx)? {
+        idx += 1;
+
+        match d.u32()? {
+            2 => {
+                // `[0, hash]` or `[1, #6.24(bytes)]`
+                let mut option = Decoder::new(next_item(&mut d, output)?);
+                option.array()?;
+
+                if option.u8()? == 1 {
+                    option.tag()?;
+                    parts.inline_datum = Some(option.bytes()?);
+                }
+            }
+            3 => {
+                d.tag()?;
+                parts.script_ref = Some(d.bytes()?);
+            }
+            _ => d.skip()?,
+        }
+    }
+
+    Ok(parts)
+}
+
+/// Original bytes of the script within a script ref, as hashed for native
+/// scripts
+pub fn script_ref_body(script_ref: &[u8]) -> DecodeResult<&[u8]> {
+    let mut d = Decoder::new(script_ref);
+    d.array()?;
+    d.skip()?;
+    next_item(&mut d, script_ref)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Post-alonzo output with a non-canonical inline datum (the int `1`
+    /// encoded in two bytes) and a native script ref
+    const OUTPUT: &str = "a400581d6111111111111111111111111111111111111111111111111111111111011a000f4240028201d81842180103d818458200820180";
+
+    /// Pre-alonzo output holding 1 ada
+    const LEGACY: &str =
+        "82581d61111111111111111111111111111111111111111111111111111111111a000f4240";
+
+    #[test]
+    fn output_parts_keep_original_bytes() {
+        let output = hex::decode(OUTPUT).unwrap();
+        let parts = output_parts(&output).unwrap();
+
+        assert_eq!(parts.inline_datum, Some(&[0x18, 0x01][..]));
+        assert_eq!(parts.script_ref, Some(&[0x82, 0x00, 0x82, 0x01, 0x80][..]));
+
+        let script = script_ref_body(parts.script_ref.unwrap()).unwrap();
+        assert_eq!(script, &[0x82, 0x01, 0x80][..]);
+    }
+
+    #[test]
+    fn legacy_outputs_have_no_parts() {
+        let output = hex::decode(LEGACY).unwrap();
+        assert_eq!(output_parts(&output).unwrap(), OutputParts::default());
+    }
+
+    #[test]
+    fn body_outputs_are_sliced_in_order() {
+        let body = hex::decode(format!("a300800182{}{}0200", LEGACY, OUTPUT)).unwrap();
+
+        let outputs = body_outputs(&body).unwrap();
+
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(hex::encode(outputs[0]), LEGACY);
+        assert_eq!(hex::encode(outputs[1]), OUTPUT);
+    }
+}
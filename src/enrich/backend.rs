@@ -0,0 +1,22 @@
+use crate::Error;
+
+/// A key-value store able to persist the UTxO set required by the enrich
+/// stage
+///
+/// Keys are output references in `{tx_hash}#{idx}` format and values are the
+/// opaque payloads produced by the enrich worker. Implementations only need to
+/// take care of persistence, the logic of which UTxOs are produced, fetched or
+/// removed is shared across all backends.
+pub trait Backend: Sized + Send + Sync {
+    type Config: Clone + Send;
+
+    fn open(config: &Self::Config) -> Result<Self, Error>;
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    fn insert_batch(&self, items: Vec<(String, Vec<u8>)>) -> Result<(), Error>;
+
+    fn remove_batch(&self, keys: Vec<String>) -> Result<(), Error>;
+
+    fn flush(&self) -> Result<(), Error>;
+}
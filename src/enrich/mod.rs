@@ -1,5 +1,14 @@
+pub mod backend;
+pub mod seed;
 pub mod skip;
 pub mod sled;
+mod worker;
+
+#[cfg(feature = "redb")]
+pub mod redb;
+
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb;
 
 use gasket::messaging::{OutputPort, TwoPhaseInputPort};
 use serde::Deserialize;
@@ -11,6 +20,12 @@ use crate::{bootstrap, crosscut, model};
 pub enum Config {
     Skip,
     Sled(sled::Config),
+
+    #[cfg(feature = "redb")]
+    Redb(redb::Config),
+
+    #[cfg(feature = "rocksdb")]
+    RocksDb(rocksdb::Config),
 }
 
 impl Default for Config {
@@ -24,6 +39,12 @@ impl Config {
         match self {
             Config::Skip => Bootstrapper::Skip(skip::Bootstrapper::default()),
             Config::Sled(c) => Bootstrapper::Sled(c.boostrapper(policy)),
+
+            #[cfg(feature = "redb")]
+            Config::Redb(c) => Bootstrapper::Redb(c.boostrapper(policy)),
+
+            #[cfg(feature = "rocksdb")]
+            Config::RocksDb(c) => Bootstrapper::RocksDb(c.boostrapper(policy)),
         }
     }
 }
@@ -31,6 +52,12 @@ impl Config {
 pub enum Bootstrapper {
     Skip(skip::Bootstrapper),
     Sled(sled::Bootstrapper),
+
+    #[cfg(feature = "redb")]
+    Redb(redb::Bootstrapper),
+
+    #[cfg(feature = "rocksdb")]
+    RocksDb(rocksdb::Bootstrapper),
 }
 
 impl Bootstrapper {
@@ -38,6 +65,12 @@ impl Bootstrapper {
         match self {
             Bootstrapper::Skip(x) => x.borrow_input_port(),
             Bootstrapper::Sled(x) => x.borrow_input_port(),
+
+            #[cfg(feature = "redb")]
+            Bootstrapper::Redb(x) => x.borrow_input_port(),
+
+            #[cfg(feature = "rocksdb")]
+            Bootstrapper::RocksDb(x) => x.borrow_input_port(),
         }
     }
 
@@ -45,6 +78,12 @@ impl Bootstrapper {
         match self {
             Bootstrapper::Skip(x) => x.borrow_output_port(),
             Bootstrapper::Sled(x) => x.borrow_output_port(),
+
+            #[cfg(feature = "redb")]
+            Bootstrapper::Redb(x) => x.borrow_output_port(),
+
+            #[cfg(feature = "rocksdb")]
+            Bootstrapper::RocksDb(x) => x.borrow_output_port(),
         }
     }
 
@@ -52,6 +91,12 @@ impl Bootstrapper {
         match self {
             Bootstrapper::Skip(x) => x.spawn_stages(pipeline),
             Bootstrapper::Sled(x) => x.spawn_stages(pipeline),
+
+            #[cfg(feature = "redb")]
+            Bootstrapper::Redb(x) => x.spawn_stages(pipeline),
+
+            #[cfg(feature = "rocksdb")]
+            Bootstrapper::RocksDb(x) => x.spawn_stages(pipeline),
         }
     }
 }
@@ -0,0 +1,89 @@
+use redb::{Database, ReadableTable, TableDefinition};
+use serde::Deserialize;
+
+use crate::crosscut;
+
+use super::{backend::Backend, seed, worker};
+
+const UTXOS: TableDefinition<&str, &[u8]> = TableDefinition::new("utxos");
+
+#[derive(Deserialize, Clone)]
+pub struct Config {
+    pub db_path: String,
+
+    /// Optional UTxO dump used to populate the db before the first block
+    pub seed: Option<seed::Config>,
+
+    /// Amount of recently produced outputs kept in memory, disabled if not set
+    pub cache_size: Option<usize>,
+}
+
+impl Config {
+    pub fn boostrapper(self, policy: &crosscut::policies::RuntimePolicy) -> Bootstrapper {
+        let seed = self.seed.clone();
+        let cache_size = self.cache_size;
+        Bootstrapper::new(self, seed, cache_size, policy, "enrich-redb")
+    }
+}
+
+pub type Bootstrapper = worker::Bootstrapper<RedbBackend>;
+
+pub struct RedbBackend(Database);
+
+impl Backend for RedbBackend {
+    type Config = Config;
+
+    fn open(config: &Self::Config) -> Result<Self, crate::Error> {
+        let db = Database::create(&config.db_path).map_err(crate::Error::storage)?;
+
+        // make sure the table exists so that readers don't fail on a fresh db
+        let wx = db.begin_write().map_err(crate::Error::storage)?;
+        wx.open_table(UTXOS).map_err(crate::Error::storage)?;
+        wx.commit().map_err(crate::Error::storage)?;
+
+        Ok(RedbBackend(db))
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, crate::Error> {
+        let rx = self.0.begin_read().map_err(crate::Error::storage)?;
+        let table = rx.open_table(UTXOS).map_err(crate::Error::storage)?;
+        let value = table.get(key).map_err(crate::Error::storage)?;
+
+        Ok(value.map(|x| x.value().to_vec()))
+    }
+
+    fn insert_batch(&self, items: Vec<(String, Vec<u8>)>) -> Result<(), crate::Error> {
+        let wx = self.0.begin_write().map_err(crate::Error::storage)?;
+
+        {
+            let mut table = wx.open_table(UTXOS).map_err(crate::Error::storage)?;
+
+            for (key, value) in items.iter() {
+                table
+                    .insert(key.as_str(), value.as_slice())
+                    .map_err(crate::Error::storage)?;
+            }
+        }
+
+        wx.commit().map_err(crate::Error::storage)
+    }
+
+    fn remove_batch(&self, keys: Vec<String>) -> Result<(), crate::Error> {
+        let wx = self.0.begin_write().map_err(crate::Error::storage)?;
+
+        {
+            let mut table = wx.open_table(UTXOS).map_err(crate::Error::storage)?;
+
+            for key in keys.iter() {
+                table.remove(key.as_str()).map_err(crate::Error::storage)?;
+            }
+        }
+
+        wx.commit().map_err(crate::Error::storage)
+    }
+
+    fn flush(&self) -> Result<(), crate::Error> {
+        // every write transaction is durable once committed
+        Ok(())
+    }
+}
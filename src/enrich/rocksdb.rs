@@ -0,0 +1,66 @@
+use rocksdb::{WriteBatch, DB};
+use serde::Deserialize;
+
+use crate::crosscut;
+
+use super::{backend::Backend, seed, worker};
+
+#[derive(Deserialize, Clone)]
+pub struct Config {
+    pub db_path: String,
+
+    /// Optional UTxO dump used to populate the db before the first block
+    pub seed: Option<seed::Config>,
+
+    /// Amount of recently produced outputs kept in memory, disabled if not set
+    pub cache_size: Option<usize>,
+}
+
+impl Config {
+    pub fn boostrapper(self, policy: &crosscut::policies::RuntimePolicy) -> Bootstrapper {
+        let seed = self.seed.clone();
+        let cache_size = self.cache_size;
+        Bootstrapper::new(self, seed, cache_size, policy, "enrich-rocksdb")
+    }
+}
+
+pub type Bootstrapper = worker::Bootstrapper<RocksDbBackend>;
+
+pub struct RocksDbBackend(DB);
+
+impl Backend for RocksDbBackend {
+    type Config = Config;
+
+    fn open(config: &Self::Config) -> Result<Self, crate::Error> {
+        let db = DB::open_default(&config.db_path).map_err(crate::Error::storage)?;
+        Ok(RocksDbBackend(db))
+    }
+
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, crate::Error> {
+        self.0.get(key.as_bytes()).map_err(crate::Error::storage)
+    }
+
+    fn insert_batch(&self, items: Vec<(String, Vec<u8>)>) -> Result<(), crate::Error> {
+        let mut batch = WriteBatch::default();
+
+        for (key, value) in items {
+            batch.put(key.as_bytes(), value);
+        }
+
+        self.0.write(batch).map_err(crate::Error::storage)
+    }
+
+    fn remove_batch(&self, keys: Vec<String>) -> Result<(), crate::Error> {
+        let mut batch = WriteBatch::default();
+
+        for key in keys {
+            batch.delete(key.as_bytes());
+        }
+
+        self.0.write(batch).map_err(crate::Error::storage)
+    }
+
+    fn flush(&self) -> Result<(), crate::Error> {
+        self.0.flush().map_err(crate::Error::storage)
+    }
+}
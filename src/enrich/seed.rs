@@ -0,0 +1,405 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    str::FromStr,
+};
+
+use pallas::{
+    codec::{
+        minicbor,
+        utils::{CborWrap, KeyValuePairs},
+    },
+    crypto::hash::Hash,
+    ledger::{
+        addresses::Address,
+        primitives::babbage::{
+            DatumOption, PlutusData, PostAlonzoTransactionOutput, TransactionOutput, Value,
+        },
+        traverse::Era,
+    },
+};
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::Error;
+
+use super::{backend::Backend, worker::UtxoValue};
+
+/// Key used to flag a db that was already seeded, it can't collide with a
+/// UTxO key because these always have the `{tx_hash}#{idx}` format
+const SEEDED_MARKER_KEY: &str = "_seeded";
+
+const SEED_BATCH_SIZE: usize = 10_000;
+
+#[derive(Deserialize, Clone)]
+pub enum Format {
+    /// One UTxO per line with format `tx_hash,index,era,cbor_hex`, where
+    /// `era` is the numeric tag used by Pallas (Byron = 0 .. Babbage = 5) and
+    /// `cbor_hex` is the raw output as found in the producing tx.
+    Csv,
+
+    /// The JSON output of `cardano-cli query utxo --whole-utxo --out-file`.
+    /// Outputs are re-encoded as Babbage outputs, reference scripts are not
+    /// supported.
+    ///
+    /// The dump doesn't carry the original encoding of the outputs, every one
+    /// of them (Byron and Shelley ones included) is rebuilt as a post-Alonzo
+    /// map with assets in sorted order. Reducers hashing or storing the raw
+    /// output bytes (eg: the `Cbor` projection of `UtxoByAddress`) see these
+    /// bytes instead of the on-chain ones, use the `Csv` format when that
+    /// matters.
+    CardanoCli,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Config {
+    pub path: String,
+    pub format: Format,
+}
+
+type Entry = (String, Vec<u8>);
+
+fn parse_csv_line(line: &str) -> Result<Entry, Error> {
+    let parts: Vec<_> = line.trim().split(',').collect();
+
+    if parts.len() != 4 {
+        return Err(Error::config(format!("invalid seed csv line: {}", line)));
+    }
+
+    let hash = Hash::<32>::from_str(parts[0]).map_err(|e| Error::config(e.to_string()))?;
+    let idx = u64::from_str(parts[1]).map_err(|e| Error::config(e.to_string()))?;
+    let era = u16::from_str(parts[2]).map_err(|e| Error::config(e.to_string()))?;
+    let cbor = hex::decode(parts[3]).map_err(|e| Error::config(e.to_string()))?;
+
+    // make sure the era is something that we'll be able to decode later
+    Era::try_from(era).map_err(|e| Error::config(e.to_string()))?;
+
+    let key = format!("{}#{}", hash, idx);
+    let value = UtxoValue(era, cbor).try_into()?;
+
+    Ok((key, value))
+}
+
+/// Feeds every entry of a csv dump to the sink, one line at a time
+fn parse_csv<R, F>(reader: R, sink: &mut F) -> Result<(), Error>
+where
+    R: BufRead,
+    F: FnMut(Entry) -> Result<(), Error>,
+{
+    for line in reader.lines() {
+        let line = line.map_err(|e| Error::config(e.to_string()))?;
+
+        if line.trim().is_empty() || line.starts_with("tx_hash") {
+            continue;
+        }
+
+        sink(parse_csv_line(&line)?)?;
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+struct CliOutput {
+    address: String,
+    // sorted, so that re-encoded outputs come out byte-for-byte the same on every run
+    value: BTreeMap<String, serde_json::Value>,
+    datumhash: Option<String>,
+    #[serde(rename = "inlineDatumRaw")]
+    inline_datum_raw: Option<String>,
+}
+
+fn parse_cli_value(value: &BTreeMap<String, serde_json::Value>) -> Result<Value, Error> {
+    let mut coin = 0;
+    let mut assets = Vec::new();
+
+    for (key, amount) in value {
+        if key == "lovelace" {
+            coin = amount
+                .as_u64()
+                .ok_or_else(|| Error::config("invalid lovelace amount"))?;
+
+            continue;
+        }
+
+        let policy = Hash::<28>::from_str(key).map_err(|e| Error::config(e.to_string()))?;
+
+        let names = amount
+            .as_object()
+            .ok_or_else(|| Error::config("invalid asset map"))?;
+
+        let mut policy_assets = Vec::new();
+
+        for (name, quantity) in names {
+            let name = hex::decode(name).map_err(|e| Error::config(e.to_string()))?;
+
+            let quantity = quantity
+                .as_u64()
+                .ok_or_else(|| Error::config("invalid asset quantity"))?;
+
+            policy_assets.push((name.into(), quantity));
+        }
+
+        assets.push((policy, KeyValuePairs::from(policy_assets)));
+    }
+
+    match assets.is_empty() {
+        true => Ok(Value::Coin(coin)),
+        false => Ok(Value::Multiasset(coin, KeyValuePairs::from(assets))),
+    }
+}
+
+fn parse_cli_output(key: &str, output: CliOutput) -> Result<Entry, Error> {
+    // mainnet dumps include Byron (base58) addresses next to Shelley (bech32) ones
+    let address = Address::from_str(&output.address).map_err(|e| Error::config(e.to_string()))?;
+
+    let value = parse_cli_value(&output.value)?;
+
+    let datum_option = match (output.inline_datum_raw, output.datumhash) {
+        (Some(raw), _) => {
+            let raw = hex::decode(raw).map_err(|e| Error::config(e.to_string()))?;
+            let data: PlutusData = minicbor::decode(&raw).map_err(Error::cbor)?;
+            Some(DatumOption::Data(CborWrap(data)))
+        }
+        (None, Some(hash)) => Some(DatumOption::Hash(
+            Hash::<32>::from_str(&hash).map_err(|e| Error::config(e.to_string()))?,
+        )),
+        _ => None,
+    };
+
+    let output = TransactionOutput::PostAlonzo(PostAlonzoTransactionOutput {
+        address: address.to_vec().into(),
+        value,
+        datum_option,
+        script_ref: None,
+    });
+
+    let cbor = minicbor::to_vec(&output).map_err(Error::cbor)?;
+    let value = UtxoValue(Era::Babbage.into(), cbor).try_into()?;
+
+    Ok((key.to_owned(), value))
+}
+
+/// Walks the top-level json map entry by entry so that the whole dump never
+/// needs to be held in memory
+struct CliVisitor<'a, F>(&'a mut F);
+
+impl<'de, 'a, F> Visitor<'de> for CliVisitor<'a, F>
+where
+    F: FnMut(Entry) -> Result<(), Error>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of utxo outputs")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        use serde::de::Error as _;
+
+        while let Some((key, output)) = map.next_entry::<String, CliOutput>()? {
+            let entry = parse_cli_output(&key, output).map_err(A::Error::custom)?;
+            (self.0)(entry).map_err(A::Error::custom)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_cardano_cli<R, F>(reader: R, sink: &mut F) -> Result<(), Error>
+where
+    R: Read,
+    F: FnMut(Entry) -> Result<(), Error>,
+{
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+
+    (&mut deserializer)
+        .deserialize_map(CliVisitor(sink))
+        .map_err(|e| Error::config(e.to_string()))?;
+
+    deserializer.end().map_err(|e| Error::config(e.to_string()))
+}
+
+/// Loads the UTxO dump into the db unless a previous run already did it
+///
+/// Returns the amount of UTxOs imported.
+pub(crate) fn seed_if_needed<B: Backend>(db: &B, config: &Config) -> Result<usize, Error> {
+    if db.get(SEEDED_MARKER_KEY)?.is_some() {
+        log::info!("enrich db already seeded, skipping {}", config.path);
+        return Ok(0);
+    }
+
+    log::warn!("seeding enrich db from {}", config.path);
+
+    let file = File::open(&config.path).map_err(|e| Error::config(e.to_string()))?;
+    let reader = BufReader::new(file);
+
+    let mut count = 0;
+    let mut batch = Vec::with_capacity(SEED_BATCH_SIZE);
+
+    let mut sink = |entry: Entry| -> Result<(), Error> {
+        batch.push(entry);
+        count += 1;
+
+        if batch.len() >= SEED_BATCH_SIZE {
+            db.insert_batch(std::mem::take(&mut batch))?;
+        }
+
+        Ok(())
+    };
+
+    match config.format {
+        Format::Csv => parse_csv(reader, &mut sink)?,
+        Format::CardanoCli => parse_cardano_cli(reader, &mut sink)?,
+    };
+
+    if !batch.is_empty() {
+        db.insert_batch(batch)?;
+    }
+
+    db.insert_batch(vec![(SEEDED_MARKER_KEY.to_owned(), vec![])])?;
+    db.flush()?;
+
+    log::warn!("enrich db seeded with {} utxos", count);
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::ledger::traverse::{Asset, MultiEraOutput};
+
+    use super::*;
+
+    #[test]
+    fn csv_line_is_parsed_into_key_and_value() {
+        let line = "1e3f0d7ebcdcc8a7a57b5e6fc3c2a2dd9b1a7ec5fb8c6a71a7c3f1b0e3a2d4c5,1,5,8200";
+
+        let (key, value) = parse_csv_line(line).unwrap();
+
+        assert_eq!(
+            key,
+            "1e3f0d7ebcdcc8a7a57b5e6fc3c2a2dd9b1a7ec5fb8c6a71a7c3f1b0e3a2d4c5#1"
+        );
+
+        let UtxoValue(era, cbor) = value.try_into().unwrap();
+        assert_eq!(era, 5);
+        assert_eq!(cbor, vec![0x82, 0x00]);
+    }
+
+    fn collect_csv(content: &str) -> Vec<Entry> {
+        let mut entries = vec![];
+        parse_csv(content.as_bytes(), &mut |x| {
+            entries.push(x);
+            Ok(())
+        })
+        .unwrap();
+        entries
+    }
+
+    #[test]
+    fn csv_header_and_empty_lines_are_skipped() {
+        let content = "tx_hash,index,era,cbor_hex\n\n";
+        assert!(collect_csv(content).is_empty());
+    }
+
+    #[test]
+    fn cli_dump_accepts_byron_and_shelley_addresses() {
+        let content = r#"{
+            "1e3f0d7ebcdcc8a7a57b5e6fc3c2a2dd9b1a7ec5fb8c6a71a7c3f1b0e3a2d4c5#0": {
+                "address": "Ae2tdPwUPEZFRbyhz3cpfC2CumGzNkFBN2L42rcUc2yjQpEkxDbkPodpMAi",
+                "value": { "lovelace": 1000000 }
+            },
+            "1e3f0d7ebcdcc8a7a57b5e6fc3c2a2dd9b1a7ec5fb8c6a71a7c3f1b0e3a2d4c5#1": {
+                "address": "addr1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers66hrl8",
+                "value": { "lovelace": 2000000 }
+            }
+        }"#;
+
+        let mut entries = vec![];
+        parse_cardano_cli(content.as_bytes(), &mut |x| {
+            entries.push(x);
+            Ok(())
+        })
+        .unwrap();
+
+        let outputs: Vec<_> = entries
+            .into_iter()
+            .map(|(_, value)| {
+                let UtxoValue(era, cbor) = value.try_into().unwrap();
+                assert_eq!(era, u16::from(Era::Babbage));
+                cbor
+            })
+            .collect();
+
+        let byron = MultiEraOutput::decode(Era::Babbage, &outputs[0]).unwrap();
+        assert_eq!(
+            byron.address().unwrap().to_string(),
+            "Ae2tdPwUPEZFRbyhz3cpfC2CumGzNkFBN2L42rcUc2yjQpEkxDbkPodpMAi"
+        );
+        assert_eq!(byron.lovelace_amount(), 1000000);
+
+        let shelley = MultiEraOutput::decode(Era::Babbage, &outputs[1]).unwrap();
+        assert_eq!(
+            shelley.address().unwrap().to_string(),
+            "addr1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers66hrl8"
+        );
+        assert_eq!(shelley.lovelace_amount(), 2000000);
+    }
+
+    #[test]
+    fn cli_assets_are_encoded_in_sorted_order() {
+        let content = format!(
+            r#"{{
+                "1e3f0d7ebcdcc8a7a57b5e6fc3c2a2dd9b1a7ec5fb8c6a71a7c3f1b0e3a2d4c5#0": {{
+                    "address": "addr1vx2fxv2umyhttkxyxp8x0dlpdt3k6cwng5pxj3jhsydzers66hrl8",
+                    "value": {{
+                        "lovelace": 2000000,
+                        "{bb}": {{ "02": 2, "01": 1 }},
+                        "{aa}": {{ "03": 3 }}
+                    }}
+                }}
+            }}"#,
+            aa = "aa".repeat(28),
+            bb = "bb".repeat(28),
+        );
+
+        let mut entries = vec![];
+        parse_cardano_cli(content.as_bytes(), &mut |x| {
+            entries.push(x);
+            Ok(())
+        })
+        .unwrap();
+
+        let (_, value) = entries.remove(0);
+        let UtxoValue(_, cbor) = value.try_into().unwrap();
+        let output = MultiEraOutput::decode(Era::Babbage, &cbor).unwrap();
+
+        let assets: Vec<_> = output
+            .non_ada_assets()
+            .into_iter()
+            .map(|x| match x {
+                Asset::NativeAsset(policy, name, quantity) => {
+                    (policy.to_string(), hex::encode(name), quantity)
+                }
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(
+            assets,
+            vec![
+                ("aa".repeat(28), "03".into(), 3),
+                ("bb".repeat(28), "01".into(), 1),
+                ("bb".repeat(28), "02".into(), 2),
+            ]
+        );
+    }
+}
@@ -74,6 +74,14 @@ impl gasket::runtime::Worker for Worker {
                 self.output
                     .send(model::EnrichedBlockPayload::roll_back(x))?;
             }
+            model::RawBlockPayload::MempoolAdd(era, tx) => {
+                self.output
+                    .send(model::EnrichedBlockPayload::mempool_add(era, tx))?;
+            }
+            model::RawBlockPayload::MempoolRemove(hash) => {
+                self.output
+                    .send(model::EnrichedBlockPayload::mempool_remove(hash))?;
+            }
         };
 
         self.input.commit();
@@ -0,0 +1,376 @@
+use std::{num::NonZeroUsize, sync::Mutex, time::Duration};
+
+use gasket::{
+    error::AsWorkError,
+    runtime::{spawn_stage, WorkOutcome},
+};
+
+use lru::LruCache;
+use pallas::{
+    codec::minicbor,
+    ledger::traverse::{Era, MultiEraBlock, MultiEraTx, OutputRef},
+};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+use crate::{
+    bootstrap, crosscut,
+    model::{self, BlockContext},
+    prelude::AppliesPolicy,
+};
+
+use super::{backend::Backend, seed};
+
+type InputPort = gasket::messaging::TwoPhaseInputPort<model::RawBlockPayload>;
+type OutputPort = gasket::messaging::OutputPort<model::EnrichedBlockPayload>;
+
+pub struct Bootstrapper<B: Backend> {
+    config: B::Config,
+    seed: Option<seed::Config>,
+    cache_size: Option<usize>,
+    policy: crosscut::policies::RuntimePolicy,
+    stage_name: &'static str,
+    input: InputPort,
+    output: OutputPort,
+}
+
+impl<B: Backend + 'static> Bootstrapper<B> {
+    pub fn new(
+        config: B::Config,
+        seed: Option<seed::Config>,
+        cache_size: Option<usize>,
+        policy: &crosscut::policies::RuntimePolicy,
+        stage_name: &'static str,
+    ) -> Self {
+        Self {
+            config,
+            seed,
+            cache_size,
+            policy: policy.clone(),
+            stage_name,
+            input: Default::default(),
+            output: Default::default(),
+        }
+    }
+
+    pub fn borrow_input_port(&mut self) -> &'_ mut InputPort {
+        &mut self.input
+    }
+
+    pub fn borrow_output_port(&mut self) -> &'_ mut OutputPort {
+        &mut self.output
+    }
+
+    pub fn spawn_stages(self, pipeline: &mut bootstrap::Pipeline) {
+        let worker = Worker::<B> {
+            config: self.config,
+            seed: self.seed,
+            policy: self.policy,
+            db: None,
+            cache: self
+                .cache_size
+                .and_then(NonZeroUsize::new)
+                .map(|size| Mutex::new(LruCache::new(size))),
+            input: self.input,
+            output: self.output,
+            inserts_counter: Default::default(),
+            remove_counter: Default::default(),
+            matches_counter: Default::default(),
+            mismatches_counter: Default::default(),
+            blocks_counter: Default::default(),
+            cache_hits_counter: Default::default(),
+            cache_misses_counter: Default::default(),
+        };
+
+        pipeline.register_stage(spawn_stage(
+            worker,
+            gasket::runtime::Policy {
+                tick_timeout: Some(Duration::from_secs(600)),
+                ..Default::default()
+            },
+            Some(self.stage_name),
+        ));
+    }
+}
+
+pub struct Worker<B: Backend> {
+    config: B::Config,
+    seed: Option<seed::Config>,
+    policy: crosscut::policies::RuntimePolicy,
+    db: Option<B>,
+    cache: Option<Mutex<LruCache<String, (u16, Vec<u8>)>>>,
+    input: InputPort,
+    output: OutputPort,
+    inserts_counter: gasket::metrics::Counter,
+    remove_counter: gasket::metrics::Counter,
+    matches_counter: gasket::metrics::Counter,
+    mismatches_counter: gasket::metrics::Counter,
+    blocks_counter: gasket::metrics::Counter,
+    cache_hits_counter: gasket::metrics::Counter,
+    cache_misses_counter: gasket::metrics::Counter,
+}
+
+pub(crate) struct UtxoValue(pub u16, pub Vec<u8>);
+
+impl TryInto<Vec<u8>> for UtxoValue {
+    type Error = crate::Error;
+
+    fn try_into(self) -> Result<Vec<u8>, Self::Error> {
+        let UtxoValue(era, body) = self;
+        minicbor::to_vec((era, body)).map_err(crate::Error::cbor)
+    }
+}
+
+impl TryFrom<Vec<u8>> for UtxoValue {
+    type Error = crate::Error;
+
+    fn try_from(value: Vec<u8>) -> Result<Self, Self::Error> {
+        let (tag, body): (u16, Vec<u8>) = minicbor::decode(&value).map_err(crate::Error::cbor)?;
+
+        Ok(UtxoValue(tag, body))
+    }
+}
+
+#[inline]
+fn fetch_referenced_utxo<B: Backend>(
+    db: &B,
+    utxo_ref: &OutputRef,
+) -> Result<Option<(OutputRef, Era, Vec<u8>)>, crate::Error> {
+    if let Some(raw) = db.get(&utxo_ref.to_string())? {
+        let UtxoValue(era, cbor) = raw.try_into().map_err(crate::Error::storage)?;
+        let era: Era = era.try_into().map_err(crate::Error::storage)?;
+        Ok(Some((utxo_ref.clone(), era, cbor)))
+    } else {
+        Ok(None)
+    }
+}
+
+impl<B: Backend> Worker<B> {
+    #[inline]
+    fn insert_produced_utxos(&self, db: &B, txs: &[MultiEraTx]) -> Result<(), crate::Error> {
+        let mut items = Vec::new();
+
+        for tx in txs.iter() {
+            // valid txs produce their outputs, phase-2 failed txs only produce
+            // their collateral return (indexed right after the regular outputs)
+            for (idx, output) in tx.produces() {
+                let key = format!("{}#{}", tx.hash(), idx);
+
+                let era = tx.era().into();
+                let body = output.encode();
+
+                if let Some(cache) = &self.cache {
+                    cache.lock().unwrap().put(key.clone(), (era, body.clone()));
+                }
+
+                let value: Vec<u8> = UtxoValue(era, body).try_into()?;
+
+                items.push((key, value));
+            }
+        }
+
+        db.insert_batch(items)?;
+
+        self.inserts_counter.inc(txs.len() as u64);
+
+        Ok(())
+    }
+
+    #[inline]
+    fn par_fetch_utxos(
+        &self,
+        db: &B,
+        refs: Vec<OutputRef>,
+    ) -> Result<Vec<Option<(OutputRef, Era, Vec<u8>)>>, crate::Error> {
+        let mut matches = Vec::with_capacity(refs.len());
+
+        // recently produced outputs are served from memory, only the rest of
+        // them need to hit the db
+        let pending = match &self.cache {
+            Some(cache) => {
+                let mut cache = cache.lock().unwrap();
+                let mut pending = Vec::new();
+
+                for utxo_ref in refs {
+                    match cache.get(&utxo_ref.to_string()) {
+                        Some((era, cbor)) => {
+                            let era: Era = (*era).try_into().map_err(crate::Error::storage)?;
+                            matches.push(Some((utxo_ref, era, cbor.clone())));
+                            self.cache_hits_counter.inc(1);
+                        }
+                        None => {
+                            pending.push(utxo_ref);
+                            self.cache_misses_counter.inc(1);
+                        }
+                    }
+                }
+
+                pending
+            }
+            None => refs,
+        };
+
+        let fetched: Result<Vec<_>, crate::Error> = pending
+            .par_iter()
+            .map(|utxo_ref| fetch_referenced_utxo(db, utxo_ref))
+            .collect();
+
+        matches.extend(fetched?);
+
+        for m in matches.iter() {
+            match m {
+                Some(_) => self.matches_counter.inc(1),
+                None => self.mismatches_counter.inc(1),
+            }
+        }
+
+        Ok(matches)
+    }
+
+    #[inline]
+    fn par_fetch_referenced_utxos(
+        &self,
+        db: &B,
+        txs: &[MultiEraTx],
+    ) -> Result<BlockContext, crate::Error> {
+        let mut ctx = BlockContext::default();
+
+        // regular inputs and collateral are both resolved since we don't know
+        // in advance which ones reducers will need, the tx validity flag
+        // decides which of them are actually spent
+        let spendable: Vec<_> = txs
+            .iter()
+            .flat_map(|tx| tx.inputs().into_iter().chain(tx.collateral()))
+            .map(|input| input.output_ref())
+            .collect();
+
+        for (key, era, cbor) in self.par_fetch_utxos(db, spendable)?.into_iter().flatten() {
+            ctx.import_ref_output(&key, era, cbor);
+        }
+
+        let references: Vec<_> = txs
+            .iter()
+            .flat_map(|tx| tx.reference_inputs())
+            .map(|input| input.output_ref())
+            .collect();
+
+        for (key, era, cbor) in self.par_fetch_utxos(db, references)?.into_iter().flatten() {
+            ctx.import_reference_output(&key, era, cbor);
+        }
+
+        Ok(ctx)
+    }
+
+    fn remove_consumed_utxos(&self, db: &B, txs: &[MultiEraTx]) -> Result<(), crate::Error> {
+        // reference inputs are never part of what a tx consumes, they stay in
+        // the db until a later tx spends them
+        let keys: Vec<_> = txs
+            .iter()
+            .flat_map(|tx| tx.consumes())
+            .map(|i| i.output_ref().to_string())
+            .collect();
+
+        let count = keys.len();
+
+        if let Some(cache) = &self.cache {
+            let mut cache = cache.lock().unwrap();
+
+            for key in keys.iter() {
+                cache.pop(key);
+            }
+        }
+
+        db.remove_batch(keys)?;
+
+        self.remove_counter.inc(count as u64);
+
+        Ok(())
+    }
+}
+
+impl<B: Backend> gasket::runtime::Worker for Worker<B> {
+    fn metrics(&self) -> gasket::metrics::Registry {
+        gasket::metrics::Builder::new()
+            .with_counter("enrich_inserts", &self.inserts_counter)
+            .with_counter("enrich_removes", &self.remove_counter)
+            .with_counter("enrich_matches", &self.matches_counter)
+            .with_counter("enrich_mismatches", &self.mismatches_counter)
+            .with_counter("enrich_blocks", &self.blocks_counter)
+            .with_counter("enrich_cache_hits", &self.cache_hits_counter)
+            .with_counter("enrich_cache_misses", &self.cache_misses_counter)
+            .build()
+    }
+
+    fn work(&mut self) -> gasket::runtime::WorkResult {
+        let msg = self.input.recv_or_idle()?;
+
+        match msg.payload {
+            model::RawBlockPayload::RollForward(cbor) => {
+                let block = MultiEraBlock::decode(&cbor)
+                    .map_err(crate::Error::cbor)
+                    .apply_policy(&self.policy)
+                    .or_panic()?;
+
+                let block = match block {
+                    Some(x) => x,
+                    None => return Ok(gasket::runtime::WorkOutcome::Partial),
+                };
+
+                let db = self.db.as_ref().unwrap();
+
+                let txs = block.txs();
+
+                // first we insert new utxo produced in this block
+                self.insert_produced_utxos(db, &txs).or_restart()?;
+
+                // then we fetch spent and referenced utxo in this block
+                let ctx = self.par_fetch_referenced_utxos(db, &txs).or_restart()?;
+
+                // and finally we remove utxos consumed by the block
+                self.remove_consumed_utxos(db, &txs).or_restart()?;
+
+                self.output
+                    .send(model::EnrichedBlockPayload::roll_forward(cbor, ctx))?;
+
+                self.blocks_counter.inc(1);
+            }
+            model::RawBlockPayload::RollBack(x) => {
+                self.output
+                    .send(model::EnrichedBlockPayload::roll_back(x))?;
+            }
+            model::RawBlockPayload::MempoolAdd(era, tx) => {
+                self.output
+                    .send(model::EnrichedBlockPayload::mempool_add(era, tx))?;
+            }
+            model::RawBlockPayload::MempoolRemove(hash) => {
+                self.output
+                    .send(model::EnrichedBlockPayload::mempool_remove(hash))?;
+            }
+        };
+
+        self.input.commit();
+        Ok(WorkOutcome::Partial)
+    }
+
+    fn bootstrap(&mut self) -> Result<(), gasket::error::Error> {
+        let db = B::open(&self.config).or_retry()?;
+
+        if let Some(seed) = &self.seed {
+            let count = seed::seed_if_needed(&db, seed).or_panic()?;
+            self.inserts_counter.inc(count as u64);
+        }
+
+        self.db = Some(db);
+
+        Ok(())
+    }
+
+    fn teardown(&mut self) -> Result<(), gasket::error::Error> {
+        match &self.db {
+            Some(db) => {
+                db.flush().or_panic()?;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Pipeline-level filter applied once between the enrich and reducer stages
+//!
+//! Txs that don't satisfy the predicate are pruned from the block context so
+//! that reducers skip them without evaluating any predicate of their own.
+
+use std::{collections::HashSet, time::Duration};
+
+use gasket::runtime::{spawn_stage, WorkOutcome};
+use pallas::ledger::traverse::MultiEraBlock;
+use serde::Deserialize;
+
+use crate::{
+    bootstrap,
+    crosscut::{self, filters::Filter},
+    model,
+    prelude::*,
+};
+
+type InputPort = gasket::messaging::TwoPhaseInputPort<model::EnrichedBlockPayload>;
+type OutputPort = gasket::messaging::OutputPort<model::EnrichedBlockPayload>;
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub predicate: crosscut::filters::Predicate,
+}
+
+impl Config {
+    pub fn bootstrapper(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<Bootstrapper, crate::Error> {
+        Ok(Bootstrapper {
+            filter: Filter::compile(&self.predicate, chain)?,
+            policy: policy.clone(),
+            input: Default::default(),
+            output: Default::default(),
+        })
+    }
+}
+
+pub struct Bootstrapper {
+    filter: Filter,
+    policy: crosscut::policies::RuntimePolicy,
+    input: InputPort,
+    output: OutputPort,
+}
+
+impl Bootstrapper {
+    pub fn borrow_input_port(&mut self) -> &'_ mut InputPort {
+        &mut self.input
+    }
+
+    pub fn borrow_output_port(&mut self) -> &'_ mut OutputPort {
+        &mut self.output
+    }
+
+    pub fn spawn_stages(self, pipeline: &mut bootstrap::Pipeline) {
+        let worker = Worker {
+            filter: self.filter,
+            policy: self.policy,
+            input: self.input,
+            output: self.output,
+            retained_counter: Default::default(),
+            dropped_counter: Default::default(),
+        };
+
+        pipeline.register_stage(spawn_stage(
+            worker,
+            gasket::runtime::Policy {
+                tick_timeout: Some(Duration::from_secs(600)),
+                ..Default::default()
+            },
+            Some("filter"),
+        ));
+    }
+}
+
+pub struct Worker {
+    filter: Filter,
+    policy: crosscut::policies::RuntimePolicy,
+    input: InputPort,
+    output: OutputPort,
+    retained_counter: gasket::metrics::Counter,
+    dropped_counter: gasket::metrics::Counter,
+}
+
+impl gasket::runtime::Worker for Worker {
+    fn metrics(&self) -> gasket::metrics::Registry {
+        gasket::metrics::Builder::new()
+            .with_counter("filter_retained_txs", &self.retained_counter)
+            .with_counter("filter_dropped_txs", &self.dropped_counter)
+            .build()
+    }
+
+    fn work(&mut self) -> gasket::runtime::WorkResult {
+        let msg = self.input.recv_or_idle()?;
+
+        match msg.payload {
+            model::EnrichedBlockPayload::RollForward(cbor, mut ctx) => {
+                let block = MultiEraBlock::decode(&cbor)
+                    .map_err(crate::Error::cbor)
+                    .apply_policy(&self.policy)
+                    .or_panic()?;
+
+                if let Some(block) = block {
+                    let mut retained = HashSet::new();
+
+                    for (idx, tx) in block.txs().iter().enumerate() {
+                        let matches = self.filter.eval(&block, tx, &ctx, &self.policy);
+
+                        if matches.or_panic()? {
+                            retained.insert(idx);
+                            self.retained_counter.inc(1);
+                        } else {
+                            self.dropped_counter.inc(1);
+                        }
+                    }
+
+                    ctx.retain_txs(retained);
+                }
+
+                // blocks are always forwarded, even if empty, so that
+                // downstream stages can keep track of the cursor
+                self.output
+                    .send(model::EnrichedBlockPayload::roll_forward(cbor, ctx))?;
+            }
+            // mempool txs are not part of a block, block-level patterns can't
+            // be evaluated for them so they go through untouched
+            x => {
+                self.output
+                    .send(gasket::messaging::Message { payload: x })?;
+            }
+        };
+
+        self.input.commit();
+        Ok(WorkOutcome::Partial)
+    }
+}
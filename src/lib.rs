@@ -1,6 +1,7 @@
 pub mod bootstrap;
 pub mod crosscut;
 pub mod enrich;
+pub mod filter;
 pub mod model;
 pub mod prelude;
 pub mod reducers;
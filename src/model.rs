@@ -1,4 +1,7 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+};
 
 use pallas::{
     ledger::traverse::{Era, MultiEraBlock, MultiEraOutput, MultiEraTx, OutputRef},
@@ -12,6 +15,8 @@ use crate::prelude::*;
 pub enum RawBlockPayload {
     RollForward(Vec<u8>),
     RollBack(Point),
+    MempoolAdd(Era, Vec<u8>),
+    MempoolRemove(Hash<32>),
 }
 
 impl RawBlockPayload {
@@ -26,11 +31,33 @@ impl RawBlockPayload {
             payload: Self::RollBack(point),
         }
     }
+
+    pub fn mempool_add(era: Era, tx: Vec<u8>) -> gasket::messaging::Message<Self> {
+        gasket::messaging::Message {
+            payload: Self::MempoolAdd(era, tx),
+        }
+    }
+
+    pub fn mempool_remove(tx_hash: Hash<32>) -> gasket::messaging::Message<Self> {
+        gasket::messaging::Message {
+            payload: Self::MempoolRemove(tx_hash),
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone)]
 pub struct BlockContext {
+    /// Resolved outputs for the inputs and collateral of the block txs, these
+    /// are the only candidates for being spent
     utxos: HashMap<String, (Era, Vec<u8>)>,
+
+    /// Resolved outputs for the reference inputs of the block txs, these are
+    /// read by scripts but never spent
+    references: HashMap<String, (Era, Vec<u8>)>,
+
+    /// Indexes of the txs that passed the pipeline-level filter, every tx is
+    /// retained if the filter stage is not present
+    retained_txs: Option<HashSet<usize>>,
 }
 
 impl BlockContext {
@@ -38,6 +65,10 @@ impl BlockContext {
         self.utxos.insert(key.to_string(), (era, cbor));
     }
 
+    pub fn import_reference_output(&mut self, key: &OutputRef, era: Era, cbor: Vec<u8>) {
+        self.references.insert(key.to_string(), (era, cbor));
+    }
+
     pub fn find_utxo(&self, key: &OutputRef) -> Result<MultiEraOutput, Error> {
         let (era, cbor) = self
             .utxos
@@ -47,10 +78,47 @@ impl BlockContext {
         MultiEraOutput::decode(*era, cbor).map_err(crate::Error::cbor)
     }
 
+    pub fn find_reference_utxo(&self, key: &OutputRef) -> Result<MultiEraOutput, Error> {
+        let (era, cbor) = self
+            .references
+            .get(&key.to_string())
+            .ok_or_else(|| Error::missing_utxo(key))?;
+
+        MultiEraOutput::decode(*era, cbor).map_err(crate::Error::cbor)
+    }
+
+    pub fn retain_txs(&mut self, idxs: HashSet<usize>) {
+        self.retained_txs = Some(idxs);
+    }
+
+    /// Whether the filter stage kept the tx at the given position of the block
+    pub fn is_retained(&self, idx: usize) -> bool {
+        match &self.retained_txs {
+            Some(idxs) => idxs.contains(&idx),
+            None => true,
+        }
+    }
+
+    /// Txs of the block that reducers should process
+    ///
+    /// Reducers that keep track of unspent outputs must still apply the spends
+    /// of the pruned txs, see `is_retained`.
+    pub fn filtered_txs<'b>(&self, block: &'b MultiEraBlock<'b>) -> Vec<MultiEraTx<'b>> {
+        block
+            .txs()
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, _)| self.is_retained(*idx))
+            .map(|(_, tx)| tx)
+            .collect()
+    }
+
     pub fn get_all_keys(&self) -> Vec<String> {
         self.utxos.keys().map(|x| x.clone()).collect()
     }
 
+    /// Outputs actually spent by the tx: its inputs if the tx is valid or its
+    /// collateral if it failed phase-2 validation
     pub fn find_consumed_txos(
         &self,
         tx: &MultiEraTx,
@@ -60,7 +128,27 @@ impl BlockContext {
             .consumes()
             .iter()
             .map(|i| i.output_ref())
-            .map(|r| self.find_utxo(&r).map(|u| (r,u)))
+            .map(|r| self.find_utxo(&r).map(|u| (r, u)))
+            .map(|r| r.apply_policy(policy))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+
+        Ok(items)
+    }
+
+    /// Outputs read by the tx through reference inputs, which remain unspent
+    pub fn find_reference_txos(
+        &self,
+        tx: &MultiEraTx,
+        policy: &RuntimePolicy,
+    ) -> Result<Vec<(OutputRef, MultiEraOutput)>, Error> {
+        let items = tx
+            .reference_inputs()
+            .iter()
+            .map(|i| i.output_ref())
+            .map(|r| self.find_reference_utxo(&r).map(|u| (r, u)))
             .map(|r| r.apply_policy(policy))
             .collect::<Result<Vec<_>, _>>()?
             .into_iter()
@@ -75,6 +163,8 @@ impl BlockContext {
 pub enum EnrichedBlockPayload {
     RollForward(Vec<u8>, BlockContext),
     RollBack(Point),
+    MempoolAdd(Era, Vec<u8>),
+    MempoolRemove(Hash<32>),
 }
 
 impl EnrichedBlockPayload {
@@ -89,6 +179,18 @@ impl EnrichedBlockPayload {
             payload: Self::RollBack(point),
         }
     }
+
+    pub fn mempool_add(era: Era, tx: Vec<u8>) -> gasket::messaging::Message<Self> {
+        gasket::messaging::Message {
+            payload: Self::MempoolAdd(era, tx),
+        }
+    }
+
+    pub fn mempool_remove(tx_hash: Hash<32>) -> gasket::messaging::Message<Self> {
+        gasket::messaging::Message {
+            payload: Self::MempoolRemove(tx_hash),
+        }
+    }
 }
 
 pub type Set = String;
@@ -96,6 +198,7 @@ pub type Member = String;
 pub type Key = String;
 pub type Delta = i64;
 pub type Timestamp = u64;
+pub type Ttl = u64;
 
 #[derive(Clone, Debug)]
 pub enum Value {
@@ -138,6 +241,14 @@ pub enum CRDTCommand {
     AnyWriteWins(Key, Value),
     // TODO make sure Value is a generic not stringly typed
     PNCounter(Key, Delta),
+    /// Increments (or decrements) a counter stored as a field of a hash
+    HashCounter(Key, Member, Delta),
+    /// Expires a key after the given amount of seconds
+    Expire(Key, Ttl),
+    /// Removes a key and whatever value it holds
+    Delete(Key),
+    /// Adds a member to a HyperLogLog, used for approximate distinct counts
+    HyperLogLogAdd(Key, Member),
     BlockFinished(Point),
 }
 
@@ -225,6 +336,45 @@ impl CRDTCommand {
         CRDTCommand::LastWriteWins(key, value.into(), ts)
     }
 
+    pub fn hash_counter(prefix: Option<&str>, key: &str, field: String, delta: i64) -> CRDTCommand {
+        let key = match prefix {
+            Some(prefix) => format!("{}.{}", prefix, key),
+            None => key.to_string(),
+        };
+
+        CRDTCommand::HashCounter(key, field, delta)
+    }
+
+    pub fn expire(prefix: Option<&str>, key: &str, ttl: Ttl) -> CRDTCommand {
+        let key = match prefix {
+            Some(prefix) => format!("{}.{}", prefix, key),
+            None => key.to_string(),
+        };
+
+        CRDTCommand::Expire(key, ttl)
+    }
+
+    pub fn delete<K>(prefix: Option<&str>, key: K) -> CRDTCommand
+    where
+        K: ToString,
+    {
+        let key = match prefix {
+            Some(prefix) => format!("{}.{}", prefix, key.to_string()),
+            None => key.to_string(),
+        };
+
+        CRDTCommand::Delete(key)
+    }
+
+    pub fn hyperloglog_add(prefix: Option<&str>, key: &str, member: String) -> CRDTCommand {
+        let key = match prefix {
+            Some(prefix) => format!("{}.{}", prefix, key),
+            None => key.to_string(),
+        };
+
+        CRDTCommand::HyperLogLogAdd(key, member)
+    }
+
     pub fn block_finished(block: &MultiEraBlock) -> CRDTCommand {
         let hash = block.hash();
         let slot = block.slot();
@@ -232,3 +382,98 @@ impl CRDTCommand {
         CRDTCommand::BlockFinished(point)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use pallas::ledger::traverse::{Era, MultiEraBlock, MultiEraTx};
+
+    use super::BlockContext;
+    use crate::crosscut::policies::RuntimePolicy;
+
+    /// Babbage tx spending `aa..aa#0`, with `bb..bb#1` as collateral and
+    /// reading `cc..cc#2` as a reference input
+    const TX: &str = "84a50081825820aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa00018002000d81825820bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb011281825820cccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccccc02a0f5f6";
+
+    /// Enterprise output holding 1 ada
+    const OUTPUT: &str =
+        "82581d61111111111111111111111111111111111111111111111111111111111a000f4240";
+
+    fn context(tx: &MultiEraTx) -> BlockContext {
+        let output = hex::decode(OUTPUT).unwrap();
+        let mut ctx = BlockContext::default();
+
+        for input in tx.inputs().iter().chain(tx.collateral().iter()) {
+            ctx.import_ref_output(&input.output_ref(), Era::Babbage, output.clone());
+        }
+
+        for input in tx.reference_inputs() {
+            ctx.import_reference_output(&input.output_ref(), Era::Babbage, output.clone());
+        }
+
+        ctx
+    }
+
+    fn consumed_and_referenced(cbor: &[u8]) -> (Vec<String>, Vec<String>) {
+        let tx = MultiEraTx::decode(Era::Babbage, cbor).unwrap();
+        let ctx = context(&tx);
+        let policy = RuntimePolicy::default();
+
+        let consumed = ctx
+            .find_consumed_txos(&tx, &policy)
+            .unwrap()
+            .into_iter()
+            .map(|(r, _)| r.to_string())
+            .collect();
+
+        let referenced = ctx
+            .find_reference_txos(&tx, &policy)
+            .unwrap()
+            .into_iter()
+            .map(|(r, _)| r.to_string())
+            .collect();
+
+        (consumed, referenced)
+    }
+
+    #[test]
+    fn valid_tx_consumes_its_inputs() {
+        let cbor = hex::decode(TX).unwrap();
+        let (consumed, referenced) = consumed_and_referenced(&cbor);
+
+        assert_eq!(consumed, vec![format!("{}#0", "aa".repeat(32))]);
+        assert_eq!(referenced, vec![format!("{}#2", "cc".repeat(32))]);
+    }
+
+    #[test]
+    fn failed_tx_consumes_its_collateral() {
+        let mut cbor = hex::decode(TX).unwrap();
+
+        // flip the is_valid flag that precedes the empty aux data
+        let flag = cbor.len() - 2;
+        cbor[flag] = 0xf4;
+
+        let (consumed, referenced) = consumed_and_referenced(&cbor);
+
+        assert_eq!(consumed, vec![format!("{}#1", "bb".repeat(32))]);
+        assert_eq!(referenced, vec![format!("{}#2", "cc".repeat(32))]);
+    }
+
+    #[test]
+    fn pruned_txs_are_left_out() {
+        let cbor = include_str!("../assets/test.block");
+        let bytes = hex::decode(cbor).unwrap();
+        let block = MultiEraBlock::decode(&bytes).unwrap();
+
+        let mut ctx = BlockContext::default();
+        assert_eq!(ctx.filtered_txs(&block).len(), 115);
+
+        ctx.retain_txs([0, 2].into_iter().collect());
+
+        assert!(ctx.is_retained(2));
+        assert!(!ctx.is_retained(1));
+
+        let hashes: Vec<_> = ctx.filtered_txs(&block).iter().map(|x| x.hash()).collect();
+        let txs = block.txs();
+        assert_eq!(hashes, vec![txs[0].hash(), txs[2].hash()]);
+    }
+}
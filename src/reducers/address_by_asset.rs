@@ -2,12 +2,12 @@ use pallas::ledger::traverse::MultiEraOutput;
 use pallas::ledger::traverse::{Asset, MultiEraBlock};
 use serde::Deserialize;
 
-use crate::{model, prelude::*};
+use crate::{crosscut, model, prelude::*};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
-    pub filter: Option<Vec<String>>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
     pub policy_id_hex: String,
     // bool convert to ascii, default true
     pub convert_to_ascii: Option<bool>,
@@ -15,6 +15,8 @@ pub struct Config {
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
     convert_to_ascii: bool,
 }
 
@@ -47,7 +49,13 @@ impl Reducer {
             return Ok(());
         }
 
-        let address = txo.address().map(|x| x.to_string()).or_panic()?;
+        let address = txo.address().or_panic()?;
+
+        if !crosscut::filters::in_scope(&self.filter, &address) {
+            return Ok(());
+        }
+
+        let address = address.to_string();
 
         for asset in asset_names {
             log::debug!("asset match found: ${asset}=>{address}");
@@ -67,10 +75,14 @@ impl Reducer {
     pub fn reduce_block<'b>(
         &mut self,
         block: &'b MultiEraBlock<'b>,
-        _ctx: &model::BlockContext,
+        ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        for tx in block.txs().iter() {
+        for tx in ctx.filtered_txs(block).iter() {
+            if !filter_matches!(self, block, tx, ctx) {
+                continue;
+            }
+
             for (_, txo) in tx.produces() {
                 self.process_txo(&txo, output)?;
             }
@@ -81,13 +93,21 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
         let convert_to_ascii = self.convert_to_ascii.unwrap_or(false);
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
         let reducer = Reducer {
             config: self,
+            filter,
+            policy: policy.clone(),
             convert_to_ascii,
         };
 
-        super::Reducer::AddressByAsset(reducer)
+        Ok(super::Reducer::AddressByAsset(reducer))
     }
 }
@@ -9,11 +9,12 @@ use crate::{crosscut, model};
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
-    pub filter: Option<crosscut::filters::Predicate>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
 }
 
@@ -46,7 +47,7 @@ impl Reducer {
     ) -> Result<(), gasket::error::Error> {
         let slot = block.slot();
 
-        for tx in block.txs() {
+        for tx in ctx.filtered_txs(block) {
             if filter_matches!(self, block, &tx, ctx) {
                 let tx_hash = tx.hash();
 
@@ -63,12 +64,19 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self, policy: &crosscut::policies::RuntimePolicy) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
         let reducer = Reducer {
             config: self,
+            filter,
             policy: policy.clone(),
         };
 
-        super::Reducer::AddressByTxo(reducer)
+        Ok(super::Reducer::AddressByTxo(reducer))
     }
 }
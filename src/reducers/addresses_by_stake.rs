@@ -1,31 +1,22 @@
-use pallas::ledger::addresses::{Address, StakeAddress};
+use pallas::ledger::addresses::Address;
 use pallas::ledger::traverse::MultiEraBlock;
 use serde::Deserialize;
 
+use crate::crosscut::addresses::any_address_to_stake_bech32;
 use crate::{crosscut, model, prelude::*};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
-    pub filter: Option<Vec<String>>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
 }
 
-fn any_address_to_stake_bech32(address: Address) -> Option<String> {
-    match address {
-        Address::Shelley(s) => match StakeAddress::try_from(s).ok() {
-            Some(x) => x.to_bech32().ok(),
-            _ => None,
-        },
-        Address::Byron(_) => None,
-        Address::Stake(_) => None,
-    }
-}
-
 impl Reducer {
     fn process_address(
         &mut self,
@@ -37,6 +28,10 @@ impl Reducer {
             return Ok(());
         }
 
+        if !crosscut::filters::in_scope(&self.filter, &address) {
+            return Ok(());
+        }
+
         let full_address = address.to_string();
         let stake_address = any_address_to_stake_bech32(address);
 
@@ -45,12 +40,6 @@ impl Reducer {
             None => return Ok(()),
         };
 
-        if let Some(stake_addresses) = &self.config.filter {
-            if let Err(_) = stake_addresses.binary_search(&stake_address) {
-                return Ok(());
-            }
-        }
-
         let crdt = model::CRDTCommand::set_add(
             self.config.key_prefix.as_deref(),
             &stake_address,
@@ -66,8 +55,12 @@ impl Reducer {
         ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        for tx in block.txs().into_iter() {
-            for (idx, produced) in tx.produces() {
+        for tx in ctx.filtered_txs(block).into_iter() {
+            if !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            for (_, produced) in tx.produces() {
                 let address = produced.address().or_panic()?;
                 self.process_address(address, output)?;
             }
@@ -78,28 +71,19 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self, policy: &crosscut::policies::RuntimePolicy) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
         let reducer = Reducer {
             config: self,
+            filter,
             policy: policy.clone(),
         };
 
-        super::Reducer::AddressesByStake(reducer)
-    }
-}
-
-#[cfg(test)]
-mod test {
-    use super::any_address_to_stake_bech32;
-    use pallas::ledger::addresses::Address;
-
-    #[test]
-    fn stake_bech32() {
-        let addr = Address::from_bech32("addr1q86gknmykuldcngv0atyy56ex598p6m8f24nf9nmehmgpgfcmswqs6wnpls37lh7s3du977cxw67a9dpndnmafjs08asyqxe39").unwrap();
-        let stake_bech32 = any_address_to_stake_bech32(addr).unwrap();
-        assert_eq!(
-            stake_bech32,
-            "stake1uyudc8qgd8fslcgl0mlggk7zl0vr8d0wjksekea75eg8n7cw33m0s"
-        );
+        Ok(super::Reducer::AddressesByStake(reducer))
     }
 }
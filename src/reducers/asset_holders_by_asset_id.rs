@@ -5,18 +5,13 @@ use serde::Deserialize;
 use crate::{crosscut, model, prelude::*};
 use pallas::crypto::hash::Hash;
 
-use crate::crosscut::epochs::block_epoch;
+use crate::crosscut::aggregation::{bucket_key, AggrType, Aggregator};
 use std::str::FromStr;
 
-#[derive(Deserialize, Copy, Clone)]
-pub enum AggrType {
-    Epoch,
-}
-
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
-    pub filter: Option<crosscut::filters::Predicate>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
     pub aggr_by: Option<AggrType>,
 
     /// Policies to match
@@ -29,28 +24,21 @@ pub struct Config {
 pub struct Reducer {
     config: Config,
     policy: crosscut::policies::RuntimePolicy,
-    chain: crosscut::ChainWellKnownInfo,
+    aggregator: Aggregator,
+    filter: Option<crosscut::filters::Filter>,
     policy_ids: Option<Vec<Hash<28>>>,
 }
 
 impl Reducer {
-    fn config_key(&self, subject: String, epoch_no: u64) -> String {
+    fn config_key(&self, subject: String, bucket: Option<&str>) -> String {
         let def_key_prefix = "asset_holders_by_asset_id";
 
-        match &self.config.aggr_by {
-            Some(aggr_type) if matches!(aggr_type, AggrType::Epoch) => {
-                return match &self.config.key_prefix {
-                    Some(prefix) => format!("{}.{}.{}", prefix, subject, epoch_no),
-                    None => format!("{}.{}", def_key_prefix.to_string(), subject),
-                };
-            }
-            _ => {
-                return match &self.config.key_prefix {
-                    Some(prefix) => format!("{}.{}", prefix, subject),
-                    None => format!("{}.{}", def_key_prefix.to_string(), subject),
-                };
-            }
+        let key = match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, subject),
+            None => format!("{}.{}", def_key_prefix.to_string(), subject),
         };
+
+        bucket_key(key, bucket)
     }
 
     fn is_policy_id_accepted(&self, policy_id: &Hash<28>) -> bool {
@@ -64,7 +52,7 @@ impl Reducer {
         &mut self,
         ctx: &model::BlockContext,
         input: &OutputRef,
-        epoch_no: u64,
+        bucket: Option<&str>,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
         let utxo = ctx.find_utxo(input).apply_policy(&self.policy).or_panic()?;
@@ -81,7 +69,7 @@ impl Reducer {
                 Asset::NativeAsset(policy_id, _, quantity) => {
                     if self.is_policy_id_accepted(&policy_id) {
                         let subject = asset.subject();
-                        let key = self.config_key(subject, epoch_no);
+                        let key = self.config_key(subject, bucket);
                         let delta = quantity as i64 * (-1);
 
                         let crdt =
@@ -100,7 +88,7 @@ impl Reducer {
     fn process_produced_txo(
         &mut self,
         tx_output: &MultiEraOutput,
-        epoch_no: u64,
+        bucket: Option<&str>,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
         let address = tx_output
@@ -113,7 +101,7 @@ impl Reducer {
                 Asset::NativeAsset(policy_id, _, quantity) => {
                     if self.is_policy_id_accepted(&policy_id) {
                         let subject = asset.subject();
-                        let key = self.config_key(subject, epoch_no);
+                        let key = self.config_key(subject, bucket);
                         let delta = quantity as i64;
 
                         let crdt =
@@ -135,17 +123,21 @@ impl Reducer {
         ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        for tx in block.txs().into_iter() {
-            if filter_matches!(self, block, &tx, ctx) {
-                let epoch_no = block_epoch(&self.chain, block);
+        let bucket = self.aggregator.bucket(block);
 
-                for consumed in tx.consumes().iter().map(|i| i.output_ref()) {
-                    self.process_consumed_txo(&ctx, &consumed, epoch_no, output)?;
-                }
+        for (idx, tx) in block.txs().into_iter().enumerate() {
+            // spent holdings are removed whatever the filter stage or the tx
+            // predicate say, otherwise the holder counts would drift
+            for consumed in tx.consumes().iter().map(|i| i.output_ref()) {
+                self.process_consumed_txo(&ctx, &consumed, bucket.as_deref(), output)?;
+            }
 
-                for (_, meo) in tx.produces() {
-                    self.process_produced_txo(&meo, epoch_no, output)?;
-                }
+            if !ctx.is_retained(idx) || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            for (_, meo) in tx.produces() {
+                self.process_produced_txo(&meo, bucket.as_deref(), output)?;
             }
         }
 
@@ -158,7 +150,7 @@ impl Config {
         self,
         chain: &crosscut::ChainWellKnownInfo,
         policy: &crosscut::policies::RuntimePolicy,
-    ) -> super::Reducer {
+    ) -> Result<super::Reducer, crate::Error> {
         let policy_ids: Option<Vec<Hash<28>>> = match &self.policy_ids_hex {
             Some(pids) => {
                 let ps = pids
@@ -171,14 +163,18 @@ impl Config {
             None => None,
         };
 
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+        let aggregator = Aggregator::new(self.aggr_by, chain);
+
         let reducer = Reducer {
             config: self,
-            chain: chain.clone(),
+            aggregator,
+            filter,
             policy: policy.clone(),
             policy_ids: policy_ids.clone(),
         };
 
-        super::Reducer::AssetHoldersByAssetId(reducer)
+        Ok(super::Reducer::AssetHoldersByAssetId(reducer))
     }
 }
 
@@ -0,0 +1,434 @@
+use std::collections::HashSet;
+
+use pallas::codec::utils::CborWrap;
+use pallas::ledger::primitives::alonzo::Metadatum;
+use pallas::ledger::primitives::babbage::{BigInt, DatumOption, PlutusData};
+use pallas::ledger::traverse::{Asset, MultiEraBlock, MultiEraTx};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::crosscut::metadata::{int_to_json, metadatum_to_json};
+use crate::{crosscut, model, prelude::*};
+
+const CIP25_LABEL: u64 = 721;
+
+/// Asset name prefix of CIP-68 reference tokens (CIP-67 label 100)
+const CIP68_REFERENCE_PREFIX: [u8; 4] = [0x00, 0x06, 0x43, 0xb0];
+
+/// Asset name prefixes of CIP-68 user tokens: NFTs (222), FTs (333) and RFTs (444)
+const CIP68_USER_PREFIXES: [[u8; 4]; 3] = [
+    [0x00, 0x0d, 0xe1, 0x40],
+    [0x00, 0x14, 0xdf, 0x10],
+    [0x00, 0x1b, 0xc2, 0x80],
+];
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+}
+
+pub struct Reducer {
+    config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+}
+
+fn metadatum_key_to_bytes(key: &Metadatum, version: i128) -> Option<Vec<u8>> {
+    match key {
+        // v2 uses raw bytes for both policy ids and asset names
+        Metadatum::Bytes(x) if version >= 2 => Some(x.to_vec()),
+        Metadatum::Text(x) => Some(x.as_bytes().to_vec()),
+        _ => None,
+    }
+}
+
+fn cip25_version(value: &Metadatum) -> i128 {
+    if let Metadatum::Map(entries) = value {
+        for (k, v) in entries.iter() {
+            if let (Metadatum::Text(k), Metadatum::Int(v)) = (k, v) {
+                if k == "version" {
+                    return i128::from(*v);
+                }
+            }
+        }
+    }
+
+    1
+}
+
+/// Extracts the `(asset id, version, metadata)` entries of a CIP-25 (label 721) value
+///
+/// Asset ids are the hex encoded concatenation of policy id and asset name.
+fn cip25_entries(value: &Metadatum) -> Vec<(String, i128, Value)> {
+    let version = cip25_version(value);
+
+    let policies = match value {
+        Metadatum::Map(x) => x,
+        _ => return vec![],
+    };
+
+    let mut out = vec![];
+
+    for (policy, assets) in policies.iter() {
+        // entries other than policies (eg: `version`) are skipped by the map check below
+        let policy = match policy {
+            Metadatum::Text(x) => x.to_lowercase(),
+            Metadatum::Bytes(x) => hex::encode(x.as_slice()),
+            _ => continue,
+        };
+
+        let assets = match assets {
+            Metadatum::Map(x) => x,
+            _ => continue,
+        };
+
+        for (name, metadata) in assets.iter() {
+            if let Some(name) = metadatum_key_to_bytes(name, version) {
+                let asset_id = format!("{}{}", policy, hex::encode(name));
+                out.push((asset_id, version, metadatum_to_json(metadata)));
+            }
+        }
+    }
+
+    out
+}
+
+fn bytes_to_json(bytes: &[u8]) -> Value {
+    match std::str::from_utf8(bytes) {
+        Ok(x) => json!(x),
+        Err(_) => json!(format!("0x{}", hex::encode(bytes))),
+    }
+}
+
+/// Renders the metadata map of a CIP-68 datum, decoding utf-8 bytes as text
+fn cip68_to_json(data: &PlutusData) -> Value {
+    match data {
+        PlutusData::Map(x) => Value::Object(
+            x.iter()
+                .map(|(k, v)| {
+                    let key = match cip68_to_json(k) {
+                        Value::String(x) => x,
+                        other => other.to_string(),
+                    };
+
+                    (key, cip68_to_json(v))
+                })
+                .collect(),
+        ),
+        PlutusData::Array(x) => Value::Array(x.iter().map(cip68_to_json).collect()),
+        PlutusData::BoundedBytes(x) => bytes_to_json(x),
+        PlutusData::BigInt(BigInt::Int(x)) => int_to_json(i128::from(*x)),
+        PlutusData::BigInt(BigInt::BigUInt(x)) => json!(format!("0x{}", hex::encode(x.to_vec()))),
+        PlutusData::BigInt(BigInt::BigNInt(x)) => json!(format!("-0x{}", hex::encode(x.to_vec()))),
+        PlutusData::Constr(x) => json!({
+            "constructor": x.tag,
+            "fields": x.fields.iter().map(cip68_to_json).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+/// Asset ids described by a CIP-68 reference token: the reference token itself
+/// and the user token sharing its name, if the tx mints or outputs it
+///
+/// The class of the user token (NFT, FT or RFT) can't be told from the
+/// reference UTxO, so it's looked up among the assets the tx moves. Updates
+/// that don't carry the user token only refresh the reference id.
+fn cip68_asset_ids(policy: &str, name: &[u8], tx_assets: &HashSet<String>) -> Vec<String> {
+    let body = hex::encode(&name[CIP68_REFERENCE_PREFIX.len()..]);
+
+    let mut out = vec![format!("{}{}", policy, hex::encode(name))];
+
+    for prefix in CIP68_USER_PREFIXES.iter() {
+        let user_id = format!("{}{}{}", policy, hex::encode(prefix), body);
+
+        if tx_assets.contains(&user_id) {
+            out.push(user_id);
+        }
+    }
+
+    out
+}
+
+/// Ids of the assets minted or output by the tx
+fn tx_asset_ids(tx: &MultiEraTx) -> HashSet<String> {
+    let mut out = HashSet::new();
+
+    if let Some(mint) = tx.mint().as_alonzo() {
+        for (policy, assets) in mint.iter() {
+            for (name, quantity) in assets.iter() {
+                if *quantity > 0 {
+                    out.insert(format!("{}{}", policy, hex::encode(name.to_vec())));
+                }
+            }
+        }
+    }
+
+    for (_, txo) in tx.produces() {
+        for asset in txo.non_ada_assets() {
+            if let Asset::NativeAsset(policy, name, _) = asset {
+                out.insert(format!("{}{}", policy, hex::encode(name)));
+            }
+        }
+    }
+
+    out
+}
+
+impl Reducer {
+    fn send_metadata(
+        &mut self,
+        asset_id: &str,
+        value: Value,
+        slot: u64,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let crdt = model::CRDTCommand::last_write_wins(
+            self.config.key_prefix.as_deref(),
+            asset_id,
+            value,
+            slot,
+        );
+
+        output.send(gasket::messaging::Message::from(crdt))
+    }
+
+    fn process_cip25(
+        &mut self,
+        tx: &MultiEraTx,
+        slot: u64,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let metadata = tx.metadata();
+
+        let value = match metadata.as_alonzo() {
+            Some(x) => x.iter().find(|(label, _)| *label == CIP25_LABEL),
+            None => None,
+        };
+
+        let value = match value {
+            Some((_, x)) => x,
+            None => return Ok(()),
+        };
+
+        // metadata is only meaningful for the assets minted by the same tx
+        let mut minted = HashSet::new();
+
+        if let Some(mint) = tx.mint().as_alonzo() {
+            for (policy, assets) in mint.iter() {
+                for (name, quantity) in assets.iter() {
+                    if *quantity > 0 {
+                        minted.insert(format!("{}{}", policy, hex::encode(name.to_vec())));
+                    }
+                }
+            }
+        }
+
+        for (asset_id, version, metadata) in cip25_entries(value) {
+            if !minted.contains(&asset_id) {
+                continue;
+            }
+
+            let value = json!({
+                "standard": "cip25",
+                "version": int_to_json(version),
+                "metadata": metadata,
+            });
+
+            self.send_metadata(&asset_id, value, slot, output)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_cip68(
+        &mut self,
+        tx: &MultiEraTx,
+        slot: u64,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let tx_assets = tx_asset_ids(tx);
+
+        for (_, txo) in tx.produces() {
+            let datum = match txo.datum() {
+                Some(DatumOption::Data(CborWrap(x))) => x,
+                _ => continue,
+            };
+
+            // reference datums are `Constr 0 [metadata, version, extra]`
+            let (metadata, version) = match &datum {
+                PlutusData::Constr(x) => match (x.fields.get(0), x.fields.get(1)) {
+                    (Some(m), Some(PlutusData::BigInt(BigInt::Int(v)))) => (m, i128::from(*v)),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+
+            for asset in txo.non_ada_assets() {
+                if let Asset::NativeAsset(policy, name, _) = asset {
+                    if !name.starts_with(&CIP68_REFERENCE_PREFIX) {
+                        continue;
+                    }
+
+                    let value = json!({
+                        "standard": "cip68",
+                        "version": int_to_json(version),
+                        "metadata": cip68_to_json(metadata),
+                    });
+
+                    for asset_id in cip68_asset_ids(&policy.to_string(), &name, &tx_assets) {
+                        self.send_metadata(&asset_id, value.clone(), slot, output)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn reduce_block<'b>(
+        &mut self,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let slot = block.slot();
+
+        for tx in ctx.filtered_txs(block).into_iter() {
+            if !tx.is_valid() || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            self.process_cip25(&tx, slot, output)?;
+            self.process_cip68(&tx, slot, output)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
+        let reducer = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+        };
+
+        Ok(super::Reducer::AssetMetadata(reducer))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use pallas::codec::utils::KeyValuePairs;
+    use pallas::ledger::primitives::alonzo::Metadatum;
+    use serde_json::json;
+
+    use pallas::ledger::primitives::babbage::{BigInt, PlutusData};
+
+    use super::{cip25_entries, cip68_asset_ids, cip68_to_json};
+
+    fn map(entries: Vec<(Metadatum, Metadatum)>) -> Metadatum {
+        Metadatum::Map(KeyValuePairs::from(entries))
+    }
+
+    #[test]
+    fn cip25_v1_uses_text_keys() {
+        let value = map(vec![(
+            Metadatum::Text("5d9d887de76a2c9d057b3e5d34d5411f7f8dc4d54f0c06e8ed2eb4a9".into()),
+            map(vec![(
+                Metadatum::Text("Nft1".into()),
+                map(vec![(
+                    Metadatum::Text("name".into()),
+                    Metadatum::Text("Nft #1".into()),
+                )]),
+            )]),
+        )]);
+
+        let entries = cip25_entries(&value);
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].0,
+            "5d9d887de76a2c9d057b3e5d34d5411f7f8dc4d54f0c06e8ed2eb4a94e667431"
+        );
+        assert_eq!(entries[0].1, 1);
+        assert_eq!(entries[0].2, json!({ "name": "Nft #1" }));
+    }
+
+    #[test]
+    fn cip25_v2_uses_byte_keys() {
+        let policy =
+            hex::decode("5d9d887de76a2c9d057b3e5d34d5411f7f8dc4d54f0c06e8ed2eb4a9").unwrap();
+
+        let value = map(vec![
+            (
+                Metadatum::Bytes(policy.into()),
+                map(vec![(
+                    Metadatum::Bytes(vec![0x01, 0x02].into()),
+                    map(vec![(
+                        Metadatum::Text("name".into()),
+                        Metadatum::Text("x".into()),
+                    )]),
+                )]),
+            ),
+            (Metadatum::Text("version".into()), Metadatum::Int(2.into())),
+        ]);
+
+        let entries = cip25_entries(&value);
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].0,
+            "5d9d887de76a2c9d057b3e5d34d5411f7f8dc4d54f0c06e8ed2eb4a90102"
+        );
+        assert_eq!(entries[0].1, 2);
+    }
+
+    #[test]
+    fn cip68_metadata_is_kept_for_the_user_token_of_the_tx() {
+        let policy = "5d9d887de76a2c9d057b3e5d34d5411f7f8dc4d54f0c06e8ed2eb4a9";
+        let name = hex::decode("000643b04e667431").unwrap();
+
+        let tx_assets: HashSet<_> = [
+            format!("{}000643b04e667431", policy),
+            format!("{}000de1404e667431", policy),
+            format!("{}000de1404e667432", policy),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            cip68_asset_ids(policy, &name, &tx_assets),
+            vec![
+                format!("{}000643b04e667431", policy),
+                format!("{}000de1404e667431", policy),
+            ]
+        );
+
+        // an update moving the reference token alone
+        assert_eq!(
+            cip68_asset_ids(policy, &name, &HashSet::new()),
+            vec![format!("{}000643b04e667431", policy)]
+        );
+    }
+
+    #[test]
+    fn cip68_ints_are_numbers() {
+        let data = PlutusData::Map(KeyValuePairs::from(vec![(
+            PlutusData::BoundedBytes(b"decimals".to_vec().into()),
+            PlutusData::BigInt(BigInt::Int(6.into())),
+        )]));
+
+        assert_eq!(cip68_to_json(&data), json!({ "decimals": 6 }));
+    }
+}
@@ -1,64 +1,96 @@
+use pallas::ledger::addresses::Address;
+use pallas::ledger::traverse::MultiEraBlock;
 use pallas::ledger::traverse::MultiEraOutput;
-use pallas::ledger::traverse::{MultiEraBlock, OutputRef};
 use serde::Deserialize;
 
+use crate::crosscut::addresses::any_address_to_stake_bech32;
+use crate::crosscut::aggregation::{bucket_key, AggrType, Aggregator};
+use crate::crosscut::assets::{asset_balance_commands, PolicyFilter};
 use crate::{crosscut, model, prelude::*};
 
+#[derive(Deserialize, Copy, Clone, PartialEq)]
+pub enum GroupBy {
+    Address,
+    StakeAddress,
+}
+
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
-    pub filter: Option<crosscut::filters::Predicate>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Track native assets in addition to lovelace
+    ///
+    /// Asset balances are kept in a hash stored at `<key>.assets`, with one
+    /// `<policy hex>.<asset name hex>` field per asset.
+    pub include_assets: Option<bool>,
+
+    /// Policies to match, see `PolicyFilter`
+    pub policy_ids_hex: Option<Vec<String>>,
+
+    /// Aggregate balances by stake address instead of full address
+    ///
+    /// Addresses without a stake part (Byron, enterprise) are always kept by
+    /// their full address.
+    pub group_by: Option<GroupBy>,
+
+    /// Keep the net change of each balance per bucket instead of the running
+    /// balance (eg: daily inflow / outflow)
+    pub aggr_by: Option<AggrType>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
+    policy_ids: PolicyFilter,
+    aggregator: Aggregator,
 }
 
 impl Reducer {
-    fn process_consumed_txo(
-        &mut self,
-        ctx: &model::BlockContext,
-        input: &OutputRef,
-        output: &mut super::OutputPort,
-    ) -> Result<(), gasket::error::Error> {
-        let utxo = ctx.find_utxo(input).apply_policy(&self.policy).or_panic()?;
-
-        let utxo = match utxo {
-            Some(x) => x,
-            None => return Ok(()),
-        };
-
-        let address = utxo.address().map(|addr| addr.to_string()).or_panic()?;
-
-        let key = match &self.config.key_prefix {
-            Some(prefix) => format!("{}.{}", prefix, address),
-            None => format!("{}.{}", "balance_by_address".to_string(), address),
-        };
-
-        let crdt = model::CRDTCommand::PNCounter(key, -1 * utxo.lovelace_amount() as i64);
-
-        output.send(gasket::messaging::Message::from(crdt))?;
+    fn config_key(&self, subject: &str) -> String {
+        match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, subject),
+            None => format!("{}.{}", "balance_by_address".to_string(), subject),
+        }
+    }
 
-        Ok(())
+    fn subject(&self, address: Address) -> String {
+        match self.config.group_by {
+            Some(GroupBy::StakeAddress) => {
+                let full = address.to_string();
+                any_address_to_stake_bech32(address).unwrap_or(full)
+            }
+            _ => address.to_string(),
+        }
     }
 
-    fn process_produced_txo(
+    fn process_txo(
         &mut self,
-        tx_output: &MultiEraOutput,
+        txo: &MultiEraOutput,
+        sign: i64,
+        bucket: Option<&str>,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        let address = tx_output.address().map(|x| x.to_string()).or_panic()?;
+        let address = txo.address().or_panic()?;
 
-        let key = match &self.config.key_prefix {
-            Some(prefix) => format!("{}.{}", prefix, address),
-            None => format!("{}.{}", "balance_by_address".to_string(), address),
-        };
+        if !crosscut::filters::in_scope(&self.filter, &address) {
+            return Ok(());
+        }
 
-        let crdt = model::CRDTCommand::PNCounter(key, tx_output.lovelace_amount() as i64);
+        let key = bucket_key(self.config_key(&self.subject(address)), bucket);
 
+        let crdt = model::CRDTCommand::PNCounter(key.clone(), sign * txo.lovelace_amount() as i64);
         output.send(gasket::messaging::Message::from(crdt))?;
 
+        if !self.config.include_assets.unwrap_or(false) {
+            return Ok(());
+        }
+
+        for crdt in asset_balance_commands(&key, txo, sign, &self.policy_ids) {
+            output.send(gasket::messaging::Message::from(crdt))?;
+        }
+
         Ok(())
     }
 
@@ -68,15 +100,22 @@ impl Reducer {
         ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        for tx in block.txs().into_iter() {
-            if filter_matches!(self, block, &tx, ctx) {
-                for consumed in tx.consumes().iter().map(|i| i.output_ref()) {
-                    self.process_consumed_txo(&ctx, &consumed, output)?;
-                }
-
-                for (_, produced) in tx.produces() {
-                    self.process_produced_txo(&produced, output)?;
-                }
+        let bucket = self.aggregator.bucket(block);
+
+        for (idx, tx) in block.txs().into_iter().enumerate() {
+            // spends are applied whatever the filter stage or the tx predicate
+            // say, only in-scope addresses are tracked either way. For phase-2
+            // failed txs this is the collateral, not the inputs
+            for (_, consumed) in ctx.find_consumed_txos(&tx, &self.policy).or_panic()? {
+                self.process_txo(&consumed, -1, bucket.as_deref(), output)?;
+            }
+
+            if !ctx.is_retained(idx) || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            for (_, produced) in tx.produces() {
+                self.process_txo(&produced, 1, bucket.as_deref(), output)?;
             }
         }
 
@@ -85,12 +124,24 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self, policy: &crosscut::policies::RuntimePolicy) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let policy_ids = PolicyFilter::compile(&self.policy_ids_hex)?;
+
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+        let aggregator = Aggregator::new(self.aggr_by, chain);
+
         let reducer = Reducer {
             config: self,
+            filter,
             policy: policy.clone(),
+            policy_ids,
+            aggregator,
         };
 
-        super::Reducer::BalanceByAddress(reducer)
+        Ok(super::Reducer::BalanceByAddress(reducer))
     }
 }
@@ -0,0 +1,150 @@
+use pallas::ledger::addresses::Address;
+use pallas::ledger::traverse::{MultiEraBlock, MultiEraOutput, MultiEraTx};
+use serde::Deserialize;
+
+use crate::crosscut::addresses::any_address_to_stake_bech32;
+use crate::crosscut::assets::{asset_balance_commands, PolicyFilter};
+use crate::{crosscut, model, prelude::*};
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Track native assets in addition to lovelace
+    ///
+    /// Asset balances are kept in a hash stored at `<key>.assets`, with one
+    /// `<policy hex>.<asset name hex>` field per asset.
+    pub include_assets: Option<bool>,
+
+    /// Policies to match, see `PolicyFilter`
+    pub policy_ids_hex: Option<Vec<String>>,
+}
+
+pub struct Reducer {
+    config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+    policy_ids: PolicyFilter,
+}
+
+impl Reducer {
+    fn config_key(&self, stake_address: &str) -> String {
+        match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, stake_address),
+            None => format!("{}.{}", "balance_by_stake".to_string(), stake_address),
+        }
+    }
+
+    fn process_txo(
+        &mut self,
+        txo: &MultiEraOutput,
+        sign: i64,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let address = txo.address().or_panic()?;
+
+        if !crosscut::filters::in_scope(&self.filter, &address) {
+            return Ok(());
+        }
+
+        // outputs without a stake part don't contribute to any stake account
+        let stake_address = match any_address_to_stake_bech32(address) {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        let key = self.config_key(&stake_address);
+
+        let crdt = model::CRDTCommand::PNCounter(key.clone(), sign * txo.lovelace_amount() as i64);
+        output.send(gasket::messaging::Message::from(crdt))?;
+
+        if !self.config.include_assets.unwrap_or(false) {
+            return Ok(());
+        }
+
+        for crdt in asset_balance_commands(&key, txo, sign, &self.policy_ids) {
+            output.send(gasket::messaging::Message::from(crdt))?;
+        }
+
+        Ok(())
+    }
+
+    fn process_withdrawals(
+        &mut self,
+        tx: &MultiEraTx,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        for (account, amount) in tx.withdrawals().collect::<Vec<_>>() {
+            let address = match Address::from_bytes(account) {
+                Ok(x @ Address::Stake(_)) => x,
+                _ => continue,
+            };
+
+            if !crosscut::filters::in_scope(&self.filter, &address) {
+                continue;
+            }
+
+            let stake_address = address.to_bech32().or_panic()?;
+
+            let key = format!("{}.withdrawals", self.config_key(&stake_address));
+            let crdt = model::CRDTCommand::PNCounter(key, amount as i64);
+
+            output.send(gasket::messaging::Message::from(crdt))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn reduce_block<'b>(
+        &mut self,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        for (idx, tx) in block.txs().into_iter().enumerate() {
+            // spends are applied whatever the filter stage or the tx predicate
+            // say, only in-scope addresses are tracked either way. For phase-2
+            // failed txs this is the collateral, not the inputs
+            for (_, consumed) in ctx.find_consumed_txos(&tx, &self.policy).or_panic()? {
+                self.process_txo(&consumed, -1, output)?;
+            }
+
+            if !ctx.is_retained(idx) || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            for (_, produced) in tx.produces() {
+                self.process_txo(&produced, 1, output)?;
+            }
+
+            // withdrawals of a phase-2 failed tx are never applied
+            if tx.is_valid() {
+                self.process_withdrawals(&tx, output)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let policy_ids = PolicyFilter::compile(&self.policy_ids_hex)?;
+
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
+        let reducer = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+            policy_ids,
+        };
+
+        Ok(super::Reducer::BalanceByStake(reducer))
+    }
+}
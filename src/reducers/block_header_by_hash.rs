@@ -7,11 +7,12 @@ use crate::{crosscut, model};
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
-    pub filter: Option<crosscut::filters::Predicate>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
 }
 
@@ -42,12 +43,19 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self, policy: &crosscut::policies::RuntimePolicy) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
         let reducer = Reducer {
             config: self,
+            filter,
             policy: policy.clone(),
         };
 
-        super::Reducer::BlockHeaderByHash(reducer)
+        Ok(super::Reducer::BlockHeaderByHash(reducer))
     }
 }
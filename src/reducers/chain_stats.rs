@@ -0,0 +1,369 @@
+use std::collections::HashSet;
+
+use pallas::ledger::traverse::{MultiEraBlock, MultiEraTx};
+use serde::Deserialize;
+
+use crate::crosscut::aggregation::{bucket_key, AggrType, Aggregator};
+use crate::{crosscut, model, prelude::*};
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Buckets to accumulate stats for, defaults to `Epoch` and `Day`
+    pub aggr_by: Option<Vec<AggrType>>,
+
+    /// Keep a HyperLogLog of the addresses involved in each bucket, requires
+    /// the consumed UTxOs to be available (enrich stage). Defaults to false.
+    ///
+    /// The enrich stage is also needed for the `fees` of Byron txs, which
+    /// don't declare them and are left out when their inputs can't be found.
+    pub active_addresses: Option<bool>,
+}
+
+pub struct Reducer {
+    config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+    aggregators: Vec<(String, Aggregator)>,
+}
+
+/// Totals of a single block, flushed as hash counter increments per bucket
+#[derive(Default)]
+struct Totals {
+    blocks: i64,
+    block_size: i64,
+    txs: i64,
+    failed_txs: i64,
+    fees: i64,
+    script_executions: i64,
+    ex_units_mem: i64,
+    ex_units_steps: i64,
+    assets_minted: i64,
+    assets_burned: i64,
+}
+
+impl Totals {
+    fn fields(&self) -> [(&'static str, i64); 10] {
+        [
+            ("blocks", self.blocks),
+            ("block_size", self.block_size),
+            ("txs", self.txs),
+            ("failed_txs", self.failed_txs),
+            ("fees", self.fees),
+            ("script_executions", self.script_executions),
+            ("ex_units_mem", self.ex_units_mem),
+            ("ex_units_steps", self.ex_units_steps),
+            ("assets_minted", self.assets_minted),
+            ("assets_burned", self.assets_burned),
+        ]
+    }
+}
+
+/// Name of the bucket kind within the keys, eg: `<prefix>.epoch.<epoch no>`
+fn aggr_label(aggr: &AggrType) -> String {
+    match aggr {
+        AggrType::Epoch => "epoch".into(),
+        AggrType::Day => "day".into(),
+        AggrType::Hour => "hour".into(),
+        AggrType::BlockRange(size) => format!("blocks_{}", size),
+    }
+}
+
+impl Reducer {
+    fn config_key(&self, subject: &str) -> String {
+        match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, subject),
+            None => format!("{}.{}", "chain_stats".to_string(), subject),
+        }
+    }
+
+    fn totals_commands(&self, key: &str, totals: &Totals) -> Vec<model::CRDTCommand> {
+        totals
+            .fields()
+            .into_iter()
+            .filter(|(_, delta)| *delta != 0)
+            .map(|(field, delta)| {
+                model::CRDTCommand::hash_counter(None, key, field.to_string(), delta)
+            })
+            .collect()
+    }
+
+    /// Fee paid by a valid tx, Byron txs don't declare it so it's the lovelace
+    /// consumed minus the lovelace produced
+    fn tx_fee(
+        &self,
+        tx: &MultiEraTx,
+        ctx: &model::BlockContext,
+    ) -> Result<i64, gasket::error::Error> {
+        if let Some(fee) = tx.fee() {
+            return Ok(fee as i64);
+        }
+
+        let consumed = ctx.find_consumed_txos(tx, &self.policy).or_panic()?;
+
+        // a partial sum would count the missing inputs as fees
+        if consumed.len() != tx.consumes().len() {
+            return Ok(0);
+        }
+
+        let consumed: u64 = consumed.iter().map(|(_, x)| x.lovelace_amount()).sum();
+        let produced: u64 = tx.produces().iter().map(|(_, x)| x.lovelace_amount()).sum();
+
+        Ok(consumed.saturating_sub(produced) as i64)
+    }
+
+    /// Stats of the whole block, the txs pruned by the filter stage included,
+    /// only this reducer's own filter applies
+    fn block_commands(
+        &self,
+        block: &MultiEraBlock,
+        ctx: &model::BlockContext,
+    ) -> Result<Vec<model::CRDTCommand>, gasket::error::Error> {
+        let track_addresses = self.config.active_addresses.unwrap_or(false);
+
+        let mut totals = Totals {
+            blocks: 1,
+            block_size: block.size() as i64,
+            ..Default::default()
+        };
+
+        let mut addresses = HashSet::new();
+
+        for tx in block.txs() {
+            if !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            totals.txs += 1;
+
+            // scripts ran either way, but failed txs pay with their collateral
+            match tx.is_valid() {
+                true => totals.fees += self.tx_fee(&tx, ctx)?,
+                false => totals.failed_txs += 1,
+            }
+
+            for redeemer in tx.redeemers().into_iter().flatten() {
+                totals.script_executions += 1;
+                totals.ex_units_mem += redeemer.ex_units.mem as i64;
+                totals.ex_units_steps += redeemer.ex_units.steps as i64;
+            }
+
+            if tx.is_valid() {
+                if let Some(mint) = tx.mint().as_alonzo() {
+                    for (_, assets) in mint.iter() {
+                        for (_, quantity) in assets.iter() {
+                            match *quantity > 0 {
+                                true => totals.assets_minted += 1,
+                                false => totals.assets_burned += 1,
+                            }
+                        }
+                    }
+                }
+            }
+
+            if track_addresses {
+                for (_, consumed) in ctx.find_consumed_txos(&tx, &self.policy).or_panic()? {
+                    addresses.insert(consumed.address().map(|x| x.to_string()).or_panic()?);
+                }
+
+                for (_, produced) in tx.produces() {
+                    addresses.insert(produced.address().map(|x| x.to_string()).or_panic()?);
+                }
+            }
+        }
+
+        let mut commands = vec![];
+
+        for (label, aggregator) in self.aggregators.iter() {
+            let key = match aggregator.bucket(block) {
+                Some(bucket) => bucket_key(self.config_key(label), Some(&bucket)),
+                None => continue,
+            };
+
+            commands.extend(self.totals_commands(&key, &totals));
+
+            for address in addresses.iter() {
+                commands.push(model::CRDTCommand::hyperloglog_add(
+                    None,
+                    &format!("{}.addresses", key),
+                    address.clone(),
+                ));
+            }
+        }
+
+        Ok(commands)
+    }
+
+    pub fn reduce_block<'b>(
+        &mut self,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        for crdt in self.block_commands(block, ctx)? {
+            output.send(gasket::messaging::Message::from(crdt))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
+        let aggregators = self
+            .aggr_by
+            .clone()
+            .unwrap_or_else(|| vec![AggrType::Epoch, AggrType::Day])
+            .into_iter()
+            .map(|x| (aggr_label(&x), Aggregator::new(Some(x), chain)))
+            .collect();
+
+        let reducer = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+            aggregators,
+        };
+
+        Ok(super::Reducer::ChainStats(reducer))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use pallas::ledger::traverse::{Era, MultiEraBlock, MultiEraTx};
+
+    use crate::{
+        crosscut::{
+            aggregation::{AggrType, Aggregator},
+            policies::{ErrorAction, RuntimePolicy},
+            ChainWellKnownInfo,
+        },
+        model::{BlockContext, CRDTCommand},
+    };
+
+    use super::{aggr_label, Config, Reducer};
+
+    fn reducer(active_addresses: Option<bool>) -> Reducer {
+        let aggr = AggrType::BlockRange(1_000_000);
+
+        Reducer {
+            config: Config {
+                key_prefix: Some("stats".into()),
+                filter: None,
+                aggr_by: Some(vec![aggr]),
+                active_addresses,
+            },
+            filter: None,
+            policy: RuntimePolicy {
+                missing_data: Some(ErrorAction::Skip),
+                ..Default::default()
+            },
+            aggregators: vec![(
+                aggr_label(&aggr),
+                Aggregator::new(Some(aggr), &ChainWellKnownInfo::mainnet()),
+            )],
+        }
+    }
+
+    #[test]
+    fn aggr_labels_are_distinct() {
+        assert_eq!(aggr_label(&AggrType::Epoch), "epoch");
+        assert_eq!(aggr_label(&AggrType::Day), "day");
+        assert_eq!(aggr_label(&AggrType::Hour), "hour");
+        assert_eq!(aggr_label(&AggrType::BlockRange(1000)), "blocks_1000");
+    }
+
+    #[test]
+    fn block_totals_and_active_addresses() {
+        let cbor = include_str!("../../assets/test.block");
+        let bytes = hex::decode(cbor).unwrap();
+        let block = MultiEraBlock::decode(&bytes).unwrap();
+        let ctx = BlockContext::default();
+
+        let key = format!(
+            "stats.blocks_1000000.{}",
+            block.number() / 1_000_000 * 1_000_000
+        );
+        let commands = reducer(Some(true)).block_commands(&block, &ctx).unwrap();
+
+        let counter = |name: &str| {
+            commands.iter().find_map(|x| match x {
+                CRDTCommand::HashCounter(k, field, delta) if *k == key && field == name => {
+                    Some(*delta)
+                }
+                _ => None,
+            })
+        };
+
+        assert_eq!(counter("blocks"), Some(1));
+        assert_eq!(counter("block_size"), Some(block.size() as i64));
+        assert_eq!(counter("txs"), Some(115));
+        assert_eq!(counter("failed_txs"), None);
+        assert!(counter("fees").unwrap() > 0);
+
+        // consumed UTxOs aren't available, only the produced addresses count
+        let expected: HashSet<_> = block
+            .txs()
+            .iter()
+            .flat_map(|tx| tx.produces())
+            .map(|(_, x)| x.address().unwrap().to_string())
+            .collect();
+
+        let added: HashSet<_> = commands
+            .iter()
+            .filter_map(|x| match x {
+                CRDTCommand::HyperLogLogAdd(k, member) if *k == format!("{}.addresses", key) => {
+                    Some(member.clone())
+                }
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(added, expected);
+    }
+
+    #[test]
+    fn active_addresses_are_opt_in() {
+        let cbor = include_str!("../../assets/test.block");
+        let bytes = hex::decode(cbor).unwrap();
+        let block = MultiEraBlock::decode(&bytes).unwrap();
+        let ctx = BlockContext::default();
+
+        let commands = reducer(None).block_commands(&block, &ctx).unwrap();
+
+        assert!(!commands.is_empty());
+        assert!(!commands
+            .iter()
+            .any(|x| matches!(x, CRDTCommand::HyperLogLogAdd(..))));
+    }
+
+    /// Byron tx spending `aa..aa#0` (3 ada) into a single 2.5 ada output
+    const BYRON_TX: &str = "8283818200d8185824825820aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa00818282d818584283581cda581707c400dde78d916d70c08013d0a8c4cf6e19b396bf15dcbd90a101581e581cb54b464472395612004e1602e8e71f1420b6e1421f39fd8a369d25a3001a087623a71a002625a0a080";
+    const BYRON_CONSUMED: &str = "8282d818584283581cda581707c400dde78d916d70c08013d0a8c4cf6e19b396bf15dcbd90a101581e581cb54b464472395612004e1602e8e71f1420b6e1421f39fd8a369d25a3001a087623a71a002dc6c0";
+
+    #[test]
+    fn byron_fees_come_from_the_consumed_utxos() {
+        let cbor = hex::decode(BYRON_TX).unwrap();
+        let tx = MultiEraTx::decode(Era::Byron, &cbor).unwrap();
+        let mut ctx = BlockContext::default();
+
+        // without the enrich stage the fee is unknown
+        assert_eq!(reducer(None).tx_fee(&tx, &ctx).unwrap(), 0);
+
+        let input = tx.consumes()[0].output_ref();
+        ctx.import_ref_output(&input, Era::Byron, hex::decode(BYRON_CONSUMED).unwrap());
+
+        assert_eq!(reducer(None).tx_fee(&tx, &ctx).unwrap(), 500_000);
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use pallas::ledger::addresses::{Address, ShelleyDelegationPart};
+use pallas::ledger::primitives::alonzo;
+use pallas::ledger::traverse::{MultiEraBlock, MultiEraOutput};
+use serde::Deserialize;
+
+use crate::crosscut::addresses::stake_credential_to_string;
+use crate::{crosscut, model, prelude::*};
+
+/// Slots of history kept per credential in the local db, blocks within this
+/// window of the tip can be replayed without counting their stake twice
+const HISTORY_SLOTS: u64 = 129_600;
+
+/// Delegations and the stake behind them, keyed by stake credential (hex)
+///
+/// - `<prefix>.<pool id>`: set of the credentials currently delegating to the
+///   pool
+/// - `<prefix>.live_stake`: sorted set of pool ids scored by the lovelace held
+///   by their delegators
+/// - `<prefix>.delegation.<credential>`: pool the credential delegates to, as a
+///   last-write-wins entry scored by slot. Deleted on deregistration.
+/// - `<prefix>.stake.<credential>`: lovelace held by outputs with the
+///   credential as their delegation part
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Path of the local db used to track current delegations and balances
+    ///
+    /// Live stake is only accurate for balances moved after the first synced
+    /// block, so the pipeline should start from the beginning of Shelley.
+    pub db_path: String,
+}
+
+pub struct Reducer {
+    config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+    credentials: sled::Tree,
+}
+
+/// Delegation and balance of a credential after a given block
+#[derive(Default, Clone, Debug, PartialEq)]
+struct CredentialState {
+    pool: Option<String>,
+    balance: i64,
+}
+
+impl CredentialState {
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = self.balance.to_be_bytes().to_vec();
+        bytes.extend(self.pool.as_deref().unwrap_or_default().as_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (balance, pool) = (bytes.get(..8)?, bytes.get(8..)?);
+
+        Some(CredentialState {
+            pool: match pool.is_empty() {
+                true => None,
+                false => Some(String::from_utf8_lossy(pool).to_string()),
+            },
+            balance: i64::from_be_bytes(balance.try_into().ok()?),
+        })
+    }
+}
+
+/// Entries sort by credential and then by slot, so the state of a credential
+/// before a block is the last entry below the key of that block
+fn state_key(cred: &str, slot: u64) -> String {
+    format!("{}.{:020}", cred, slot)
+}
+
+fn address_to_credential(address: &Address) -> Option<String> {
+    match address {
+        Address::Shelley(x) => match x.delegation() {
+            ShelleyDelegationPart::Key(x) => Some(x.to_string()),
+            ShelleyDelegationPart::Script(x) => Some(x.to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl Reducer {
+    fn config_key(&self, subject: &str) -> String {
+        match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, subject),
+            None => format!("{}.{}", "delegators_by_pool".to_string(), subject),
+        }
+    }
+
+    /// State of the credential as left by the blocks before the given slot
+    ///
+    /// Reading below the slot (instead of the latest entry) keeps replays of
+    /// already processed blocks from applying their changes twice.
+    fn state_before(&self, cred: &str, slot: u64) -> Result<CredentialState, gasket::error::Error> {
+        let entry = self
+            .credentials
+            .range(state_key(cred, 0)..state_key(cred, slot))
+            .next_back()
+            .transpose()
+            .map_err(crate::Error::storage)
+            .or_panic()?;
+
+        let state = entry
+            .and_then(|(_, value)| CredentialState::decode(&value))
+            .unwrap_or_default();
+
+        Ok(state)
+    }
+
+    fn state<'a>(
+        &self,
+        states: &'a mut HashMap<String, CredentialState>,
+        cred: &str,
+        slot: u64,
+    ) -> Result<&'a mut CredentialState, gasket::error::Error> {
+        if !states.contains_key(cred) {
+            let state = self.state_before(cred, slot)?;
+            states.insert(cred.to_string(), state);
+        }
+
+        Ok(states.get_mut(cred).unwrap())
+    }
+
+    /// Stores the state of each credential touched by the block and prunes
+    /// the entries that fell out of the history window
+    fn persist(
+        &self,
+        states: HashMap<String, CredentialState>,
+        slot: u64,
+    ) -> Result<(), gasket::error::Error> {
+        let mut batch = sled::Batch::default();
+
+        for (cred, state) in states {
+            batch.insert(state_key(&cred, slot).as_bytes(), state.encode());
+
+            // the newest entry out of the window is the base of the older blocks
+            let horizon = state_key(&cred, slot.saturating_sub(HISTORY_SLOTS));
+
+            let stale: Vec<_> = self
+                .credentials
+                .range(state_key(&cred, 0)..horizon)
+                .keys()
+                .collect::<Result<_, _>>()
+                .map_err(crate::Error::storage)
+                .or_panic()?;
+
+            for key in stale.into_iter().rev().skip(1) {
+                batch.remove(key);
+            }
+        }
+
+        self.credentials
+            .apply_batch(batch)
+            .map_err(crate::Error::storage)
+            .or_panic()
+    }
+
+    fn move_stake(
+        &self,
+        pool: &str,
+        delta: i64,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let key = self.config_key("live_stake");
+
+        let crdt = match delta {
+            0 => return Ok(()),
+            x if x < 0 => model::CRDTCommand::SortedSetRemove(key, pool.to_string(), x),
+            x => model::CRDTCommand::SortedSetAdd(key, pool.to_string(), x),
+        };
+
+        output.send(gasket::messaging::Message::from(crdt))
+    }
+
+    fn process_txo(
+        &self,
+        states: &mut HashMap<String, CredentialState>,
+        slot: u64,
+        txo: &MultiEraOutput,
+        sign: i64,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let address = txo.address().or_panic()?;
+
+        if !crosscut::filters::in_scope(&self.filter, &address) {
+            return Ok(());
+        }
+
+        let cred = match address_to_credential(&address) {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        let delta = sign * txo.lovelace_amount() as i64;
+
+        let key = self.config_key(&format!("stake.{}", cred));
+        let crdt = model::CRDTCommand::PNCounter(key, delta);
+        output.send(gasket::messaging::Message::from(crdt))?;
+
+        let state = self.state(states, &cred, slot)?;
+        state.balance += delta;
+
+        if let Some(pool) = state.pool.clone() {
+            self.move_stake(&pool, delta, output)?;
+        }
+
+        Ok(())
+    }
+
+    /// Takes the credential and its stake out of its current pool, if any
+    fn leave_pool(
+        &self,
+        cred: &str,
+        state: &CredentialState,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let pool = match &state.pool {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        let crdt = model::CRDTCommand::set_remove(None, &self.config_key(pool), cred.to_string());
+        output.send(gasket::messaging::Message::from(crdt))?;
+
+        self.move_stake(pool, -state.balance, output)
+    }
+
+    fn delegate(
+        &self,
+        states: &mut HashMap<String, CredentialState>,
+        slot: u64,
+        cred: &str,
+        pool: &str,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let state = self.state(states, cred, slot)?;
+
+        if state.pool.as_deref() == Some(pool) {
+            return Ok(());
+        }
+
+        self.leave_pool(cred, state, output)?;
+
+        let crdt = model::CRDTCommand::set_add(None, &self.config_key(pool), cred.to_string());
+        output.send(gasket::messaging::Message::from(crdt))?;
+
+        self.move_stake(pool, state.balance, output)?;
+
+        let crdt = model::CRDTCommand::last_write_wins(
+            None,
+            &self.config_key(&format!("delegation.{}", cred)),
+            pool.to_string(),
+            slot,
+        );
+
+        output.send(gasket::messaging::Message::from(crdt))?;
+
+        state.pool = Some(pool.to_string());
+
+        Ok(())
+    }
+
+    fn undelegate(
+        &self,
+        states: &mut HashMap<String, CredentialState>,
+        slot: u64,
+        cred: &str,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let state = self.state(states, cred, slot)?;
+
+        self.leave_pool(cred, state, output)?;
+
+        let crdt =
+            model::CRDTCommand::delete(None, self.config_key(&format!("delegation.{}", cred)));
+
+        output.send(gasket::messaging::Message::from(crdt))?;
+
+        state.pool = None;
+
+        Ok(())
+    }
+
+    pub fn reduce_block<'b>(
+        &mut self,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let slot = block.slot();
+        let mut states = HashMap::new();
+
+        for (idx, tx) in block.txs().into_iter().enumerate() {
+            // spends are applied whatever the filter stage or the tx predicate
+            // say. For phase-2 failed txs this is the collateral, not the inputs
+            for (_, consumed) in ctx.find_consumed_txos(&tx, &self.policy).or_panic()? {
+                self.process_txo(&mut states, slot, &consumed, -1, output)?;
+            }
+
+            if !ctx.is_retained(idx) || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            for (_, produced) in tx.produces() {
+                self.process_txo(&mut states, slot, &produced, 1, output)?;
+            }
+
+            if !tx.is_valid() {
+                continue;
+            }
+
+            for cert in tx.certs() {
+                match cert.as_alonzo() {
+                    Some(alonzo::Certificate::StakeDelegation(cred, pool)) => {
+                        let cred = stake_credential_to_string(cred);
+                        self.delegate(&mut states, slot, &cred, &pool.to_string(), output)?;
+                    }
+                    Some(alonzo::Certificate::StakeDeregistration(cred)) => {
+                        let cred = stake_credential_to_string(cred);
+                        self.undelegate(&mut states, slot, &cred, output)?;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        self.persist(states, slot)
+    }
+}
+
+impl Config {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
+        let db = sled::open(&self.db_path).map_err(crate::Error::storage)?;
+        let credentials = db.open_tree("credentials").map_err(crate::Error::storage)?;
+
+        let reducer = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+            credentials,
+        };
+
+        Ok(super::Reducer::DelegatorsByPool(reducer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{state_key, CredentialState};
+
+    #[test]
+    fn credential_state_roundtrip() {
+        let delegated = CredentialState {
+            pool: Some("pool1xyz".into()),
+            balance: -42,
+        };
+
+        let bytes = delegated.encode();
+        assert_eq!(CredentialState::decode(&bytes), Some(delegated));
+
+        let idle = CredentialState::default();
+        assert_eq!(CredentialState::decode(&idle.encode()), Some(idle));
+    }
+
+    #[test]
+    fn state_keys_sort_by_slot() {
+        let cred = "ab".repeat(28);
+        assert!(state_key(&cred, 9) < state_key(&cred, 10));
+        assert!(state_key(&cred, 10) < state_key(&cred, u64::MAX));
+    }
+}
@@ -0,0 +1,386 @@
+use std::collections::HashMap;
+
+use pallas::ledger::traverse::MultiEraBlock;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{crosscut, model, prelude::*};
+
+use super::liquidity_by_token_pair::{
+    compile_sources, get_pool_state, model::PoolState, DexConfig, Source,
+};
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Same pool selection as the `LiquidityByTokenPair` reducer
+    pub dex_prefix: Option<String>,
+    pub pool_currency_symbol: Option<String>,
+    pub dexes: Option<Vec<DexConfig>>,
+}
+
+pub struct Reducer {
+    config: Config,
+    sources: Vec<Source>,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+}
+
+/// Net trade of a single pool within a tx, amounts are in the normalized order
+/// of the pair (see `PoolState::normalized`)
+#[derive(Debug, PartialEq)]
+pub struct Swap {
+    /// true when token `a` was sold to the pool in exchange for token `b`
+    pub a_to_b: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+impl Swap {
+    /// Derives the trade out of the reserves before and after the tx
+    ///
+    /// Txs where both reserves move in the same direction add or remove
+    /// liquidity and aren't considered swaps. Batched orders are reported as
+    /// their net effect on the pool.
+    pub fn between(before: &PoolState, after: &PoolState) -> Option<Self> {
+        let a_delta = after.a_amount as i128 - before.a_amount as i128;
+        let b_delta = after.b_amount as i128 - before.b_amount as i128;
+
+        match (a_delta.signum(), b_delta.signum()) {
+            (1, -1) => Some(Swap {
+                a_to_b: true,
+                amount_in: a_delta as u64,
+                amount_out: -b_delta as u64,
+            }),
+            (-1, 1) => Some(Swap {
+                a_to_b: false,
+                amount_in: b_delta as u64,
+                amount_out: -a_delta as u64,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Amount of token `b` paid per unit of token `a`, not adjusted by decimals
+    pub fn price(&self) -> f64 {
+        match self.a_to_b {
+            true => self.amount_out as f64 / self.amount_in as f64,
+            false => self.amount_in as f64 / self.amount_out as f64,
+        }
+    }
+}
+
+type PoolIdentity = (Option<String>, String, String);
+
+/// Pools are matched across the tx by dex, pair and id
+fn pool_identity(state: &PoolState) -> Option<PoolIdentity> {
+    Some((state.dex.clone(), state.pair.key()?, state.id().to_string()))
+}
+
+/// Pairs the state of each pool consumed by a tx with the one it produced
+///
+/// Pools without an id nor an NFT of their own are identified by their
+/// address. When several of them share it within a single tx (eg: batched
+/// orders against two pools of the same pair) there's no telling which output
+/// continues which input, so they are left out instead of guessed.
+fn pool_transitions(
+    consumed: Vec<PoolState>,
+    produced: Vec<PoolState>,
+) -> Vec<(PoolState, PoolState)> {
+    let mut before: HashMap<PoolIdentity, Vec<PoolState>> = HashMap::new();
+
+    for state in consumed {
+        if let Some(identity) = pool_identity(&state) {
+            before.entry(identity).or_default().push(state);
+        }
+    }
+
+    let mut after: HashMap<PoolIdentity, Vec<PoolState>> = HashMap::new();
+
+    for state in produced {
+        if let Some(identity) = pool_identity(&state) {
+            after.entry(identity).or_default().push(state);
+        }
+    }
+
+    let mut transitions = vec![];
+
+    for (identity, mut produced) in after {
+        let mut consumed = match before.remove(&identity) {
+            Some(x) => x,
+            None => continue,
+        };
+
+        if consumed.len() == 1 && produced.len() == 1 {
+            transitions.push((consumed.remove(0), produced.remove(0)));
+        }
+    }
+
+    transitions
+}
+
+impl Reducer {
+    fn send_swap(
+        &mut self,
+        before: &PoolState,
+        after: &PoolState,
+        swap: Swap,
+        slot: u64,
+        tx_hash: &str,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let pair_key = match after.pair.key() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        let (sold, bought) = match swap.a_to_b {
+            true => (&after.pair.a, &after.pair.b),
+            false => (&after.pair.b, &after.pair.a),
+        };
+
+        // the tx hash and pool keep members unique, the slot orders them
+        let value = json!({
+            "dex": after.dex,
+            "pool_id": after.id(),
+            "slot": slot,
+            "tx_hash": tx_hash,
+            "token_a": after.pair.a.to_string(),
+            "token_b": after.pair.b.to_string(),
+            "sold": sold.to_string(),
+            "bought": bought.to_string(),
+            "amount_in": swap.amount_in.to_string(),
+            "amount_out": swap.amount_out.to_string(),
+            "price": swap.price(),
+            "reserves_before": [before.a_amount.to_string(), before.b_amount.to_string()],
+            "reserves_after": [after.a_amount.to_string(), after.b_amount.to_string()],
+        });
+
+        let crdt = model::CRDTCommand::sorted_set_add(
+            self.config.key_prefix.as_deref(),
+            &pair_key,
+            value.to_string(),
+            slot as i64,
+        );
+
+        output.send(gasket::messaging::Message::from(crdt))
+    }
+
+    pub fn reduce_block<'b>(
+        &mut self,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let slot = block.slot();
+
+        for tx in ctx.filtered_txs(block).into_iter() {
+            // failed txs only move collateral, pools are never touched
+            if !tx.is_valid() || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            let mut consumed = vec![];
+
+            for (_, utxo) in ctx.find_consumed_txos(&tx, &self.policy).or_panic()? {
+                if let Ok(state) = get_pool_state(&self.sources, &tx, &utxo) {
+                    consumed.push(state.normalized());
+                }
+            }
+
+            if consumed.is_empty() {
+                continue;
+            }
+
+            let produced = tx
+                .produces()
+                .iter()
+                .filter_map(|(_, utxo)| get_pool_state(&self.sources, &tx, utxo).ok())
+                .map(PoolState::normalized)
+                .collect();
+
+            let tx_hash = tx.hash().to_string();
+
+            for (before, after) in pool_transitions(consumed, produced) {
+                if let Some(swap) = Swap::between(&before, &after) {
+                    self.send_swap(&before, &after, swap, slot, &tx_hash, output)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+        let sources = compile_sources(&self.pool_currency_symbol, &self.dexes, &self.dex_prefix)?;
+
+        let reducer = Reducer {
+            config: self,
+            sources,
+            filter,
+            policy: policy.clone(),
+        };
+
+        Ok(super::Reducer::DexSwaps(reducer))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pallas::ledger::traverse::{Era, MultiEraOutput, MultiEraTx};
+
+    use crate::reducers::liquidity_by_token_pair::{
+        compile_sources, get_pool_state,
+        model::{PoolAsset, PoolState, TokenPair},
+        utils::pool_asset_from,
+        Dex, DexConfig,
+    };
+
+    use super::{pool_transitions, Swap};
+
+    /// Minswap v2 pool of ada/MIN holding 5000 ada and 2000 MIN
+    const MINSWAP_V2_BEFORE: &str = "a300581d71ea07b733d932129c378af627436e7cbc2ef0bf96e0036bb51b3bde6b01821b000000012a33b8c0a2581cf5808c2c990d86da54bfc97d89cee6efa20cd8461616359478d96b4ca1434d535001581c29d222ce763455e3d7a09a665ce554f00ac89d2e99a1a83d267170c6a1434d494e1a77359400028201d8185875d8799fd8799fd87a9f581cea07b733d932129c378af627436e7cbc2ef0bf96e0036bb51b3bde6bffffd8799f4040ffd8799f581c29d222ce763455e3d7a09a665ce554f00ac89d2e99a1a83d267170c6434d494eff1a000f42401b000000012a05f2001a77359400181e181ed87a9fffd87a9fffff";
+
+    /// Tx producing the same pool after 100 ada were sold to it for 39.100339 MIN
+    const MINSWAP_V2_SWAP_TX: &str = "84a30081825820dddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddddd000181a300581d71ea07b733d932129c378af627436e7cbc2ef0bf96e0036bb51b3bde6b01821b00000001302999c0a2581cf5808c2c990d86da54bfc97d89cee6efa20cd8461616359478d96b4ca1434d535001581c29d222ce763455e3d7a09a665ce554f00ac89d2e99a1a83d267170c6a1434d494e1a74e0f44d028201d8185875d8799fd8799fd87a9f581cea07b733d932129c378af627436e7cbc2ef0bf96e0036bb51b3bde6bffffd8799f4040ffd8799f581c29d222ce763455e3d7a09a665ce554f00ac89d2e99a1a83d267170c6434d494eff1a000f42401b000000012ffbd3001a74e0f44d181e181ed87a9fffd87a9fffff021a00030d40a0f5f6";
+
+    fn min_token() -> PoolAsset {
+        pool_asset_from(
+            &String::from("29d222ce763455e3d7a09a665ce554f00ac89d2e99a1a83d267170c6"),
+            &String::from("4d494e"),
+        )
+        .unwrap()
+    }
+
+    fn state(a_amount: u64, b_amount: u64) -> PoolState {
+        PoolState {
+            dex: Some("min".into()),
+            pool_id: None,
+            address: "addr1".into(),
+            pair: TokenPair {
+                a: PoolAsset::Ada,
+                b: min_token(),
+            },
+            a_amount,
+            b_amount,
+            fee: None,
+            lp_token: None,
+        }
+    }
+
+    #[test]
+    fn swap_direction_and_price() {
+        let swap = Swap::between(&state(1000, 500), &state(1100, 455)).unwrap();
+        assert_eq!(
+            swap,
+            Swap {
+                a_to_b: true,
+                amount_in: 100,
+                amount_out: 45
+            }
+        );
+        assert_eq!(swap.price(), 0.45);
+
+        let swap = Swap::between(&state(1000, 500), &state(900, 560)).unwrap();
+        assert_eq!(
+            swap,
+            Swap {
+                a_to_b: false,
+                amount_in: 60,
+                amount_out: 100
+            }
+        );
+        assert_eq!(swap.price(), 0.6);
+    }
+
+    #[test]
+    fn liquidity_changes_are_not_swaps() {
+        assert_eq!(Swap::between(&state(1000, 500), &state(2000, 1000)), None);
+        assert_eq!(Swap::between(&state(1000, 500), &state(500, 250)), None);
+        assert_eq!(Swap::between(&state(1000, 500), &state(1000, 500)), None);
+    }
+
+    #[test]
+    fn test_decoding_pool_transition() {
+        let sources = compile_sources(
+            &None,
+            &Some(vec![DexConfig {
+                dex: Dex::MinswapV2,
+                pool_currency_symbol: Some(
+                    "f5808c2c990d86da54bfc97d89cee6efa20cd8461616359478d96b4c".into(),
+                ),
+                pool_script_hash: None,
+                dex_prefix: Some("min".into()),
+            }]),
+            &None,
+        )
+        .unwrap();
+
+        let cbor = hex::decode(MINSWAP_V2_SWAP_TX).unwrap();
+        let tx = MultiEraTx::decode(Era::Babbage, &cbor).unwrap();
+
+        let cbor = hex::decode(MINSWAP_V2_BEFORE).unwrap();
+        let utxo = MultiEraOutput::decode(Era::Babbage, &cbor).unwrap();
+        let before = get_pool_state(&sources, &tx, &utxo).unwrap().normalized();
+
+        let produced = tx.produces();
+        let after = get_pool_state(&sources, &tx, &produced[0].1)
+            .unwrap()
+            .normalized();
+
+        let transitions = pool_transitions(vec![before], vec![after]);
+        assert_eq!(transitions.len(), 1);
+
+        let (before, after) = &transitions[0];
+        assert_eq!(before.pair.b, min_token());
+        assert_eq!((before.a_amount, before.b_amount), (5000000000, 2000000000));
+        assert_eq!((after.a_amount, after.b_amount), (5100000000, 1960899661));
+
+        assert_eq!(
+            Swap::between(before, after),
+            Some(Swap {
+                a_to_b: true,
+                amount_in: 100000000,
+                amount_out: 39100339
+            })
+        );
+    }
+
+    #[test]
+    fn pools_are_matched_by_id() {
+        let pool = |id: &str, a_amount: u64, b_amount: u64| PoolState {
+            pool_id: Some(id.into()),
+            ..state(a_amount, b_amount)
+        };
+
+        let mut transitions = pool_transitions(
+            vec![pool("x", 1000, 500), pool("y", 2000, 800)],
+            vec![pool("y", 1900, 850), pool("x", 1100, 455)],
+        );
+
+        transitions.sort_by(|a, b| a.0.pool_id.cmp(&b.0.pool_id));
+
+        assert_eq!(transitions.len(), 2);
+        assert_eq!(transitions[0].1, pool("x", 1100, 455));
+        assert_eq!(transitions[1].1, pool("y", 1900, 850));
+    }
+
+    #[test]
+    fn pools_sharing_an_address_are_not_guessed() {
+        let transitions = pool_transitions(
+            vec![state(1000, 500), state(2000, 800)],
+            vec![state(1900, 850), state(1100, 455)],
+        );
+
+        assert!(transitions.is_empty());
+    }
+}
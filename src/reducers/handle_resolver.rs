@@ -0,0 +1,220 @@
+use pallas::crypto::hash::Hash;
+use pallas::ledger::traverse::{Asset, MultiEraBlock, MultiEraOutput};
+use serde::Deserialize;
+use std::str::FromStr;
+
+use crate::{crosscut, model, prelude::*};
+
+/// CIP-67 label of CIP-68 reference tokens, they never represent a handle
+const REFERENCE_TOKEN_LABEL: u16 = 100;
+
+/// Spent handles are removed from the set of their previous address, which
+/// requires the `enrich` stage to resolve the inputs of each tx
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Policy id of the handles, defaults to the `adahandle_policy` of the chain
+    pub policy_id_hex: Option<String>,
+}
+
+pub struct Reducer {
+    config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+    handle_policy: Hash<28>,
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| match crc & 0x80 {
+            0 => crc << 1,
+            _ => (crc << 1) ^ 0x07,
+        })
+    })
+}
+
+/// Splits a CIP-67 label from an asset name, if present
+///
+/// Labels are encoded in the first 4 bytes as `0` + 16-bit label + crc-8 + `0`
+/// (in nibbles), names without a valid label are returned untouched.
+fn split_cip67_label(name: &[u8]) -> (Option<u16>, &[u8]) {
+    if name.len() < 4 || name[0] & 0xf0 != 0 || name[3] & 0x0f != 0 {
+        return (None, name);
+    }
+
+    let label = ((name[0] as u16) << 12) | ((name[1] as u16) << 4) | ((name[2] as u16) >> 4);
+
+    let checksum = (name[2] << 4) | (name[3] >> 4);
+
+    if crc8(&label.to_be_bytes()) != checksum {
+        return (None, name);
+    }
+
+    (Some(label), &name[4..])
+}
+
+impl Reducer {
+    fn config_key(&self, subject: &str) -> String {
+        match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, subject),
+            None => format!("{}.{}", "handle_resolver".to_string(), subject),
+        }
+    }
+
+    fn handles(&self, txo: &MultiEraOutput) -> Vec<String> {
+        txo.non_ada_assets()
+            .into_iter()
+            .filter_map(|asset| match asset {
+                Asset::NativeAsset(policy, name, _) if policy == self.handle_policy => {
+                    match split_cip67_label(&name) {
+                        (Some(REFERENCE_TOKEN_LABEL), _) => None,
+                        (_, name) => String::from_utf8(name.to_vec()).ok(),
+                    }
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn process_consumed_txo(
+        &mut self,
+        txo: &MultiEraOutput,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let handles = self.handles(txo);
+
+        if handles.is_empty() {
+            return Ok(());
+        }
+
+        let address = txo.address().map(|x| x.to_string()).or_panic()?;
+
+        for handle in handles {
+            let crdt = model::CRDTCommand::set_remove(
+                None,
+                &self.config_key(&format!("address.{}", address)),
+                handle,
+            );
+
+            output.send(gasket::messaging::Message::from(crdt))?;
+        }
+
+        Ok(())
+    }
+
+    fn process_produced_txo(
+        &mut self,
+        txo: &MultiEraOutput,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let handles = self.handles(txo);
+
+        if handles.is_empty() {
+            return Ok(());
+        }
+
+        let address = txo.address().map(|x| x.to_string()).or_panic()?;
+
+        for handle in handles {
+            log::debug!("handle match found: ${handle}=>{address}");
+
+            let crdt = model::CRDTCommand::any_write_wins(
+                None,
+                self.config_key(&format!("handle.{}", handle)),
+                address.clone(),
+            );
+
+            output.send(gasket::messaging::Message::from(crdt))?;
+
+            let crdt = model::CRDTCommand::set_add(
+                None,
+                &self.config_key(&format!("address.{}", address)),
+                handle,
+            );
+
+            output.send(gasket::messaging::Message::from(crdt))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn reduce_block<'b>(
+        &mut self,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        for (idx, tx) in block.txs().into_iter().enumerate() {
+            // for phase-2 failed txs this is the collateral, not the inputs
+            for (_, consumed) in ctx.find_consumed_txos(&tx, &self.policy).or_panic()? {
+                self.process_consumed_txo(&consumed, output)?;
+            }
+
+            // handles leaving an address are removed whatever the filter stage
+            // or the tx predicate say
+            if !ctx.is_retained(idx) || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            for (_, produced) in tx.produces() {
+                self.process_produced_txo(&produced, output)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
+        let handle_policy = self
+            .policy_id_hex
+            .as_deref()
+            .unwrap_or(&chain.adahandle_policy);
+
+        let handle_policy = Hash::<28>::from_str(handle_policy).map_err(|_| {
+            crate::Error::config(format!("invalid handle policy id {}", handle_policy))
+        })?;
+
+        let reducer = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+            handle_policy,
+        };
+
+        Ok(super::Reducer::HandleResolver(reducer))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::split_cip67_label;
+
+    #[test]
+    fn cip67_labels_are_stripped() {
+        let name = hex::decode("000de1406a6f686e").unwrap();
+        assert_eq!(split_cip67_label(&name), (Some(222), "john".as_bytes()));
+
+        let name = hex::decode("000643b06a6f686e").unwrap();
+        assert_eq!(split_cip67_label(&name), (Some(100), "john".as_bytes()));
+    }
+
+    #[test]
+    fn plain_names_are_untouched() {
+        assert_eq!(split_cip67_label(b"john"), (None, "john".as_bytes()));
+        assert_eq!(split_cip67_label(b"jo"), (None, "jo".as_bytes()));
+
+        // right shape but wrong checksum
+        let name = hex::decode("000de1506a6f686e").unwrap();
+        assert_eq!(split_cip67_label(&name), (None, name.as_slice()));
+    }
+}
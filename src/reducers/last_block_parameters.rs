@@ -3,15 +3,19 @@ use serde::Deserialize;
 
 use crate::crosscut::epochs::block_epoch;
 use crate::model::Value;
+use crate::prelude::*;
 use crate::{crosscut, model};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
     chain: crosscut::ChainWellKnownInfo,
 }
 
@@ -119,8 +123,12 @@ impl Reducer {
     pub fn reduce_block<'b>(
         &mut self,
         block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
+        if !filter_matches_block!(self, block, ctx) {
+            return Ok(());
+        }
 
         let def_key_prefix = "last_block";
 
@@ -142,14 +150,20 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self,
-         chain: &crosscut::ChainWellKnownInfo
-         ) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
         let reducer = Reducer {
             config: self,
+            filter,
+            policy: policy.clone(),
             chain: chain.clone(),
         };
 
-        super::Reducer::LastBlockParameters(reducer)
+        Ok(super::Reducer::LastBlockParameters(reducer))
     }
 }
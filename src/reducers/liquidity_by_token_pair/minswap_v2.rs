@@ -0,0 +1,72 @@
+use pallas::ledger::primitives::babbage::PlutusData;
+
+use super::{model::PoolAsset, utils::plutus_int};
+
+/// Denominator of the trading fee numerators of Minswap V2 pools
+const FEE_DENOMINATOR: i128 = 10000;
+
+#[derive(Debug, PartialEq)]
+pub struct MinswapV2PoolDatum {
+    pub a: PoolAsset,
+    pub b: PoolAsset,
+    pub reserve_a: u64,
+    pub reserve_b: u64,
+    pub fee: f64,
+}
+
+impl TryFrom<&PlutusData> for MinswapV2PoolDatum {
+    type Error = ();
+
+    fn try_from(value: &PlutusData) -> Result<Self, Self::Error> {
+        if let PlutusData::Constr(pd) = value {
+            // field 0 is the stake credential of the batcher, field 3 the total liquidity
+            let a = PoolAsset::try_from(pd.fields.get(1).ok_or(())?)?;
+            let b = PoolAsset::try_from(pd.fields.get(2).ok_or(())?)?;
+
+            let int_field = |idx: usize| pd.fields.get(idx).and_then(plutus_int).ok_or(());
+
+            let reserve_a = u64::try_from(int_field(4)?).map_err(|_| ())?;
+            let reserve_b = u64::try_from(int_field(5)?).map_err(|_| ())?;
+            let fee_num = int_field(6)?;
+
+            return Ok(Self {
+                a,
+                b,
+                reserve_a,
+                reserve_b,
+                fee: fee_num as f64 / FEE_DENOMINATOR as f64,
+            });
+        }
+
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pallas::ledger::primitives::{babbage::PlutusData, Fragment};
+
+    use crate::reducers::liquidity_by_token_pair::{
+        minswap_v2::MinswapV2PoolDatum, model::PoolAsset, utils::pool_asset_from,
+    };
+
+    #[test]
+    fn test_decoding_pool_datum_ada_min() {
+        let hex_pool_datum = "d8799fd8799fd87a9f581cea07b733d932129c378af627436e7cbc2ef0bf96e0036bb51b3bde6bffffd8799f4040ffd8799f581c29d222ce763455e3d7a09a665ce554f00ac89d2e99a1a83d267170c6434d494eff1a000f42401b000000012a05f2001a77359400181e181ed87a9fffd87a9fffff";
+        let data = hex::decode(hex_pool_datum).unwrap();
+        let plutus_data = PlutusData::decode_fragment(&data).unwrap();
+        let pool_datum = MinswapV2PoolDatum::try_from(&plutus_data).unwrap();
+
+        assert_eq!(PoolAsset::Ada, pool_datum.a);
+
+        let min_token = pool_asset_from(
+            &String::from("29d222ce763455e3d7a09a665ce554f00ac89d2e99a1a83d267170c6"),
+            &String::from("4d494e"),
+        )
+        .unwrap();
+        assert_eq!(min_token, pool_datum.b);
+        assert_eq!(5000000000, pool_datum.reserve_a);
+        assert_eq!(2000000000, pool_datum.reserve_b);
+        assert_eq!(f64::from(0.003), pool_datum.fee);
+    }
+}
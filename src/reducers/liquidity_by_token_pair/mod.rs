@@ -1,84 +1,401 @@
-use pallas::ledger::{
-    primitives::babbage::PlutusData,
-    traverse::{Asset, MultiEraBlock, MultiEraOutput, MultiEraTx},
+use pallas::{
+    crypto::hash::Hash,
+    ledger::{
+        addresses::{Address, ShelleyPaymentPart},
+        primitives::babbage::{AssetName, PlutusData},
+        traverse::{Asset, MultiEraBlock, MultiEraOutput, MultiEraTx},
+    },
 };
 use serde::Deserialize;
+use std::str::FromStr;
 
 pub mod minswap;
+pub mod minswap_v2;
 pub mod model;
 pub mod muesliswap;
+pub mod spectrum;
 pub mod sundaeswap;
+pub mod sundaeswap_v3;
 pub mod utils;
+pub mod vyfi;
 pub mod wingriders;
 
 use crate::{crosscut, prelude::*};
 
 use self::{
     minswap::MinSwapPoolDatum,
-    model::{LiquidityPoolDatum, TokenPair},
+    minswap_v2::MinswapV2PoolDatum,
+    model::{LiquidityPoolDatum, PoolAsset, PoolState, TokenPair},
     muesliswap::MuesliSwapPoolDatum,
+    spectrum::SpectrumPoolDatum,
     sundaeswap::SundaePoolDatum,
-    utils::{build_key_value_pair, contains_currency_symbol, get_asset_amount, resolve_datum},
+    sundaeswap_v3::SundaeV3PoolDatum,
+    utils::{
+        build_key_value_pair, contains_currency_symbol, get_asset_amount, pool_asset_from,
+        resolve_datum,
+    },
+    vyfi::VyFiPoolDatum,
     wingriders::WingriderPoolDatum,
 };
 
+#[derive(Deserialize, Clone, Copy, PartialEq)]
+pub enum Mode {
+    /// A set of json members per token pair (the original layout)
+    Members,
+    /// A json document per pool under `<pool_prefix>.pool.<pair key>.<pool id>`
+    Pools,
+}
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum Dex {
+    Minswap,
+    MinswapV2,
+    MuesliSwap,
+    SundaeSwap,
+    SundaeSwapV3,
+    Spectrum,
+    VyFinance,
+    WingRiders,
+}
+
+impl Dex {
+    fn parse(&self, data: &PlutusData) -> Result<LiquidityPoolDatum, ()> {
+        match self {
+            Dex::Minswap => MinSwapPoolDatum::try_from(data).map(LiquidityPoolDatum::Minswap),
+            Dex::MinswapV2 => MinswapV2PoolDatum::try_from(data).map(LiquidityPoolDatum::MinswapV2),
+            Dex::MuesliSwap => {
+                MuesliSwapPoolDatum::try_from(data).map(LiquidityPoolDatum::MuesliSwapPoolDatum)
+            }
+            Dex::SundaeSwap => SundaePoolDatum::try_from(data).map(LiquidityPoolDatum::Sundaeswap),
+            Dex::SundaeSwapV3 => {
+                SundaeV3PoolDatum::try_from(data).map(LiquidityPoolDatum::SundaeswapV3)
+            }
+            Dex::Spectrum => SpectrumPoolDatum::try_from(data).map(LiquidityPoolDatum::Spectrum),
+            Dex::VyFinance => VyFiPoolDatum::try_from(data).map(LiquidityPoolDatum::VyFinance),
+            Dex::WingRiders => {
+                WingriderPoolDatum::try_from(data).map(LiquidityPoolDatum::Wingriders)
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct DexConfig {
+    pub dex: Dex,
+
+    /// Hex-encoded policy id of the token that marks the pool UTxOs of this dex
+    pub pool_currency_symbol: Option<String>,
+
+    /// Hex-encoded hash of the pool validator, for dexs where each pool is
+    /// marked by a token of its own policy (eg: Spectrum)
+    pub pool_script_hash: Option<String>,
+
+    /// Redis member prefix of this dex, defaults to `dex_prefix`
+    pub dex_prefix: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct Config {
     pub pool_prefix: Option<String>,
     pub dex_prefix: Option<String>,
-    pub pool_currency_symbol: String,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Policy id marking the pools of any of the original dexs (Minswap,
+    /// MuesliSwap, SundaeSwap and WingRiders), the datum decides which one
+    pub pool_currency_symbol: Option<String>,
+
+    /// Dexs to observe, each one with its own pool marker
+    pub dexes: Option<Vec<DexConfig>>,
+
+    /// How pool states are stored, defaults to `Members`
+    pub mode: Option<Mode>,
+
+    /// Keep a time-series of reserves per pair as a sorted set scored by slot
+    /// under `<pool_prefix>.history.<pair key>`
+    pub price_history: Option<bool>,
+}
+
+/// A way of recognizing pool UTxOs and decoding their datums
+pub struct Source {
+    /// `None` for the legacy `pool_currency_symbol`, any original datum is accepted
+    dex: Option<Dex>,
+    currency_symbol: Option<String>,
+    script_hash: Option<Hash<28>>,
+    dex_prefix: Option<String>,
+}
+
+impl Source {
+    fn matches(&self, utxo: &MultiEraOutput) -> bool {
+        if let Some(currency_symbol) = &self.currency_symbol {
+            if contains_currency_symbol(currency_symbol, &utxo.non_ada_assets()) {
+                return true;
+            }
+        }
+
+        if let (Some(expected), Ok(Address::Shelley(addr))) = (&self.script_hash, utxo.address()) {
+            if let ShelleyPaymentPart::Script(hash) = addr.payment() {
+                return hash.eq(expected);
+            }
+        }
+
+        false
+    }
+
+    fn owns(&self, policy: &Hash<28>) -> bool {
+        self.currency_symbol.as_deref() == Some(policy.to_string().as_str())
+    }
+}
+
+/// The NFT telling apart pools that share a script address (eg: Minswap v1,
+/// MuesliSwap): the one unit token held by the UTxO besides the pool marker
+/// and the pair
+fn pool_nft(source: &Source, utxo: &MultiEraOutput, pair: &TokenPair) -> Option<String> {
+    let candidates: Vec<PoolAsset> = utxo
+        .non_ada_assets()
+        .into_iter()
+        .filter_map(|asset| match asset {
+            Asset::NativeAsset(policy, name, 1) if !source.owns(&policy) => {
+                Some(PoolAsset::AssetClass(policy, AssetName::from(name)))
+            }
+            _ => None,
+        })
+        .filter(|x| *x != pair.a && *x != pair.b)
+        .collect();
+
+    match candidates.as_slice() {
+        [x] => Some(x.to_string()),
+        _ => None,
+    }
+}
+
+/// Decodes the state of a pool UTxO, if it matches any of the sources
+pub fn get_pool_state(
+    sources: &[Source],
+    tx: &MultiEraTx,
+    utxo: &MultiEraOutput,
+) -> Result<PoolState, ()> {
+    let source = sources.iter().find(|x| x.matches(utxo)).ok_or(())?;
+
+    // Get embedded datum for txIns or inline datums if applicable
+    let plutus_data: PlutusData = resolve_datum(utxo, tx)?;
+    // Decode datum as a liquidity pool datum of the matching dex
+    let pool_datum = match source.dex {
+        Some(dex) => dex.parse(&plutus_data)?,
+        None => LiquidityPoolDatum::try_from(&plutus_data)?,
+    };
+
+    let assets: Vec<Asset> = utxo.assets();
+    let address = utxo.address().map(|x| x.to_string()).map_err(|_| ())?;
+
+    let amount_of = |asset: &PoolAsset| get_asset_amount(asset, &assets).ok_or(());
+
+    let state = |a: PoolAsset, b: PoolAsset, a_amount: u64, b_amount: u64| {
+        let pair = TokenPair { a, b };
+
+        PoolState {
+            dex: source.dex_prefix.clone(),
+            pool_id: pool_nft(source, utxo, &pair),
+            address: address.clone(),
+            pair,
+            a_amount,
+            b_amount,
+            fee: None,
+            lp_token: None,
+        }
+    };
+
+    match pool_datum {
+        LiquidityPoolDatum::MuesliSwapPoolDatum(MuesliSwapPoolDatum { a, b })
+        | LiquidityPoolDatum::Minswap(MinSwapPoolDatum { a, b })
+        | LiquidityPoolDatum::Wingriders(WingriderPoolDatum { a, b }) => {
+            let (a_amount, b_amount) = (amount_of(&a)?, amount_of(&b)?);
+            Ok(state(a, b, a_amount, b_amount))
+        }
+        LiquidityPoolDatum::Sundaeswap(SundaePoolDatum { a, b, fee, pool_id }) => {
+            let (a_amount, b_amount) = (amount_of(&a)?, amount_of(&b)?);
+
+            Ok(PoolState {
+                fee: Some(fee),
+                pool_id: Some(pool_id),
+                ..state(a, b, a_amount, b_amount)
+            })
+        }
+        LiquidityPoolDatum::MinswapV2(MinswapV2PoolDatum {
+            a,
+            b,
+            reserve_a,
+            reserve_b,
+            fee,
+        }) => {
+            // the pool UTxO also holds min-ada and fees, the datum tracks the reserves
+            Ok(PoolState {
+                fee: Some(fee),
+                ..state(a, b, reserve_a, reserve_b)
+            })
+        }
+        LiquidityPoolDatum::SundaeswapV3(datum) => {
+            let (mut a_amount, mut b_amount) = (amount_of(&datum.a)?, amount_of(&datum.b)?);
+
+            // protocol fees are collected in lovelace next to the ada reserve
+            match (&datum.a, &datum.b) {
+                (PoolAsset::Ada, _) => a_amount = a_amount.saturating_sub(datum.protocol_fees),
+                (_, PoolAsset::Ada) => b_amount = b_amount.saturating_sub(datum.protocol_fees),
+                _ => (),
+            }
+
+            // LP tokens are minted by the pool validator, the one marking the pools
+            let lp_token = match (&source.currency_symbol, datum.lp_token_name()) {
+                (Some(policy), Some(name)) => pool_asset_from(policy, &hex::encode(name)),
+                _ => None,
+            };
+
+            Ok(PoolState {
+                fee: Some(datum.fee),
+                pool_id: Some(datum.pool_id),
+                lp_token,
+                ..state(datum.a, datum.b, a_amount, b_amount)
+            })
+        }
+        LiquidityPoolDatum::Spectrum(SpectrumPoolDatum { nft, a, b, lp, fee }) => {
+            // the identity nft is unique per pool, it must be held by the UTxO
+            amount_of(&nft)?;
+
+            let (a_amount, b_amount) = (amount_of(&a)?, amount_of(&b)?);
+
+            Ok(PoolState {
+                fee: Some(fee),
+                pool_id: Some(nft.to_string()),
+                lp_token: Some(lp),
+                ..state(a, b, a_amount, b_amount)
+            })
+        }
+        LiquidityPoolDatum::VyFinance(VyFiPoolDatum {
+            treasury_a,
+            treasury_b,
+            ..
+        }) => {
+            // only ada pairs are supported, the token is the one asset besides the pool nft
+            let tokens: Vec<PoolAsset> = utxo
+                .non_ada_assets()
+                .into_iter()
+                .filter_map(|asset| match asset {
+                    Asset::NativeAsset(policy, name, _) if !source.owns(&policy) => {
+                        Some(PoolAsset::AssetClass(policy, AssetName::from(name)))
+                    }
+                    _ => None,
+                })
+                .collect();
+
+            let token = match tokens.as_slice() {
+                [x] => x.clone(),
+                _ => return Err(()),
+            };
+
+            let a_amount = amount_of(&PoolAsset::Ada)?.saturating_sub(treasury_a);
+            let b_amount = amount_of(&token)?.saturating_sub(treasury_b);
+
+            Ok(state(PoolAsset::Ada, token, a_amount, b_amount))
+        }
+    }
 }
 
 pub struct Reducer {
     config: Config,
+    sources: Vec<Source>,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
 }
 
 impl Reducer {
-    fn get_key_value_pair(
-        &self,
-        tx: &MultiEraTx,
-        utxo: &MultiEraOutput,
-    ) -> Result<(String, String), ()> {
-        if !contains_currency_symbol(&self.config.pool_currency_symbol, &utxo.non_ada_assets()) {
-            return Err(());
+    fn get_key_value_pair(&self, state: &PoolState) -> Result<(String, String), ()> {
+        build_key_value_pair(
+            &state.pair,
+            &state.dex,
+            Some(state.a_amount),
+            Some(state.b_amount),
+            state.fee,
+            state.pool_id.clone(),
+        )
+        .ok_or(())
+    }
+
+    fn pool_key(&self, state: &PoolState) -> Option<String> {
+        let pair_key = state.pair.key()?;
+        Some(format!("pool.{}.{}", pair_key, state.id()))
+    }
+
+    fn process_consumed(
+        &mut self,
+        state: PoolState,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let pool_prefix = self.config.pool_prefix.as_deref();
+
+        match self.config.mode.unwrap_or(Mode::Members) {
+            Mode::Members => {
+                if let Ok((k, v)) = self.get_key_value_pair(&state) {
+                    output
+                        .send(crate::model::CRDTCommand::set_remove(pool_prefix, &k, v).into())?;
+                }
+            }
+            Mode::Pools => {
+                if let Some(key) = self.pool_key(&state.normalized()) {
+                    output.send(crate::model::CRDTCommand::delete(pool_prefix, key).into())?;
+                }
+            }
         }
 
-        // Get embedded datum for txIns or inline datums if applicable
-        let plutus_data: PlutusData = resolve_datum(utxo, tx)?;
-        // Try to decode datum as known liquidity pool datum
-        let pool_datum = LiquidityPoolDatum::try_from(&plutus_data)?;
-        let assets: Vec<Asset> = utxo.assets();
-        match pool_datum {
-            LiquidityPoolDatum::MuesliSwapPoolDatum(MuesliSwapPoolDatum { a, b })
-            | LiquidityPoolDatum::Minswap(MinSwapPoolDatum { a, b })
-            | LiquidityPoolDatum::Wingriders(WingriderPoolDatum { a, b }) => {
-                let a_amount_opt: Option<u64> = get_asset_amount(&a, &assets);
-                let b_amount_opt: Option<u64> = get_asset_amount(&b, &assets);
-                return build_key_value_pair(
-                    &TokenPair { a, b },
-                    &self.config.dex_prefix,
-                    a_amount_opt,
-                    b_amount_opt,
-                    None,
-                    None,
-                )
-                .ok_or(());
+        Ok(())
+    }
+
+    fn process_produced(
+        &mut self,
+        state: PoolState,
+        slot: u64,
+        tx_hash: &str,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let pool_prefix = self.config.pool_prefix.as_deref();
+
+        match self.config.mode.unwrap_or(Mode::Members) {
+            Mode::Members => {
+                if let Ok((k, v)) = self.get_key_value_pair(&state) {
+                    output.send(crate::model::CRDTCommand::set_add(pool_prefix, &k, v).into())?;
+                }
+            }
+            Mode::Pools => {
+                let normalized = state.clone().normalized();
+
+                if let Some(key) = self.pool_key(&normalized) {
+                    let value = normalized.to_json();
+                    let crdt = crate::model::CRDTCommand::any_write_wins(pool_prefix, key, value);
+                    output.send(crdt.into())?;
+                }
             }
-            LiquidityPoolDatum::Sundaeswap(SundaePoolDatum { a, b, fee, pool_id }) => {
-                let a_amount_opt: Option<u64> = get_asset_amount(&a, &assets);
-                let b_amount_opt: Option<u64> = get_asset_amount(&b, &assets);
-                return build_key_value_pair(
-                    &TokenPair { a, b },
-                    &self.config.dex_prefix,
-                    a_amount_opt,
-                    b_amount_opt,
-                    Some(fee),
-                    Some(pool_id),
-                )
-                .ok_or(());
+        }
+
+        if self.config.price_history.unwrap_or(false) {
+            let state = state.normalized();
+
+            if let Some(pair_key) = state.pair.key() {
+                // members carry the slot and tx so that each sample is unique
+                let mut sample = state.to_json();
+                sample["slot"] = serde_json::json!(slot);
+                sample["tx_hash"] = serde_json::json!(tx_hash);
+                sample["ratio"] = serde_json::json!(state.ratio());
+
+                let crdt = crate::model::CRDTCommand::sorted_set_add(
+                    pool_prefix,
+                    &format!("history.{}", pair_key),
+                    sample.to_string(),
+                    slot as i64,
+                );
+
+                output.send(crdt.into())?;
             }
-        };
+        }
+
+        Ok(())
     }
 
     pub fn reduce_block<'b>(
@@ -87,23 +404,28 @@ impl Reducer {
         ctx: &crate::model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        let pool_prefix: Option<&str> = self.config.pool_prefix.as_deref();
-        for tx in block.txs().into_iter() {
+        let slot = block.slot();
+
+        for (idx, tx) in block.txs().into_iter().enumerate() {
+            let tx_hash = tx.hash().to_string();
+
             for consumed in tx.consumes().iter().map(|i| i.output_ref()) {
                 if let Some(Some(utxo)) = ctx.find_utxo(&consumed).apply_policy(&self.policy).ok() {
-                    if let Some((k, v)) = self.get_key_value_pair(&tx, &utxo).ok() {
-                        output.send(
-                            crate::model::CRDTCommand::set_remove(pool_prefix, &k, v).into(),
-                        )?;
+                    if let Some(state) = get_pool_state(&self.sources, &tx, &utxo).ok() {
+                        self.process_consumed(state, output)?;
                     }
                 }
             }
 
+            // consumed pool states are removed whatever the filter stage or the
+            // tx predicate say
+            if !ctx.is_retained(idx) || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
             for (_, produced) in tx.produces() {
-                if let Some((k, v)) = self.get_key_value_pair(&tx, &produced).ok() {
-                    output.send(
-                        crate::model::CRDTCommand::set_add(pool_prefix, &k.as_str(), v).into(),
-                    )?;
+                if let Some(state) = get_pool_state(&self.sources, &tx, &produced).ok() {
+                    self.process_produced(state, slot, &tx_hash, output)?;
                 }
             }
         }
@@ -112,12 +434,105 @@ impl Reducer {
     }
 }
 
+/// Builds the pool sources out of the legacy `pool_currency_symbol` and the
+/// list of `dexes`, at least one of them is required
+pub fn compile_sources(
+    pool_currency_symbol: &Option<String>,
+    dexes: &Option<Vec<DexConfig>>,
+    dex_prefix: &Option<String>,
+) -> Result<Vec<Source>, crate::Error> {
+    let mut sources = vec![];
+
+    if let Some(currency_symbol) = pool_currency_symbol {
+        sources.push(Source {
+            dex: None,
+            currency_symbol: Some(currency_symbol.clone()),
+            script_hash: None,
+            dex_prefix: dex_prefix.clone(),
+        });
+    }
+
+    for dex in dexes.iter().flatten() {
+        let script_hash = match &dex.pool_script_hash {
+            Some(x) => Some(
+                Hash::<28>::from_str(x)
+                    .map_err(|_| crate::Error::config(format!("invalid pool script hash {}", x)))?,
+            ),
+            None => None,
+        };
+
+        if dex.pool_currency_symbol.is_none() && script_hash.is_none() {
+            return Err(crate::Error::config(format!(
+                "{:?} requires pool_currency_symbol or pool_script_hash",
+                dex.dex
+            )));
+        }
+
+        sources.push(Source {
+            dex: Some(dex.dex),
+            currency_symbol: dex.pool_currency_symbol.clone(),
+            script_hash,
+            dex_prefix: dex.dex_prefix.clone().or_else(|| dex_prefix.clone()),
+        });
+    }
+
+    if sources.is_empty() {
+        return Err(crate::Error::config(
+            "pool_currency_symbol or dexes is required",
+        ));
+    }
+
+    Ok(sources)
+}
+
 impl Config {
-    pub fn plugin(self, policy: &crosscut::policies::RuntimePolicy) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+        let sources = compile_sources(&self.pool_currency_symbol, &self.dexes, &self.dex_prefix)?;
+
         let reducer = Reducer {
             config: self,
+            sources,
+            filter,
             policy: policy.clone(),
         };
-        super::Reducer::LiquidityByTokenPair(reducer)
+
+        Ok(super::Reducer::LiquidityByTokenPair(reducer))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pallas::ledger::traverse::{Era, MultiEraOutput};
+
+    use super::{model::TokenPair, pool_asset_from, pool_nft, PoolAsset, Source};
+
+    #[test]
+    fn pools_sharing_an_address_are_told_apart_by_their_nft() {
+        // script output holding ada, the pool marker `aa..aa.MS`, the pool nft
+        // `bb..bb.0102` and 500 units of the `cc..cc.TOK` token
+        let cbor = hex::decode("82581d7111111111111111111111111111111111111111111111111111111111821a001e8480a3581caaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa1424d5301581cbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbba142010201581ccccccccccccccccccccccccccccccccccccccccccccccccccccccccca143544f4b1901f4").unwrap();
+        let utxo = MultiEraOutput::decode(Era::Babbage, &cbor).unwrap();
+
+        let source = Source {
+            dex: None,
+            currency_symbol: Some("aa".repeat(28)),
+            script_hash: None,
+            dex_prefix: None,
+        };
+
+        let pair = TokenPair {
+            a: PoolAsset::Ada,
+            b: pool_asset_from(&"cc".repeat(28), &hex::encode("TOK")).unwrap(),
+        };
+
+        assert_eq!(
+            pool_nft(&source, &utxo, &pair),
+            Some(format!("{}.0102", "bb".repeat(28)))
+        );
     }
 }
@@ -6,8 +6,9 @@ use pallas::{
 use std::{fmt, str::FromStr};
 
 use super::{
-    minswap::MinSwapPoolDatum, muesliswap::MuesliSwapPoolDatum, sundaeswap::SundaePoolDatum,
-    wingriders::WingriderPoolDatum,
+    minswap::MinSwapPoolDatum, minswap_v2::MinswapV2PoolDatum, muesliswap::MuesliSwapPoolDatum,
+    spectrum::SpectrumPoolDatum, sundaeswap::SundaePoolDatum, sundaeswap_v3::SundaeV3PoolDatum,
+    vyfi::VyFiPoolDatum, wingriders::WingriderPoolDatum,
 };
 
 pub enum LiquidityPoolDatum {
@@ -15,8 +16,15 @@ pub enum LiquidityPoolDatum {
     Minswap(MinSwapPoolDatum),
     Sundaeswap(SundaePoolDatum),
     Wingriders(WingriderPoolDatum),
+    MinswapV2(MinswapV2PoolDatum),
+    SundaeswapV3(SundaeV3PoolDatum),
+    Spectrum(SpectrumPoolDatum),
+    VyFinance(VyFiPoolDatum),
 }
 
+/// Tries the datums of the original set of dexs (Minswap, MuesliSwap, SundaeSwap and
+/// WingRiders). Newer datums are too loosely shaped to be told apart reliably, they
+/// are only decoded when the dex is selected explicitly (see `Dex`).
 impl TryFrom<&PlutusData> for LiquidityPoolDatum {
     type Error = ();
 
@@ -102,6 +110,72 @@ impl TryFrom<&PlutusData> for TokenPair {
     }
 }
 
+/// Snapshot of the reserves of a single liquidity pool
+#[derive(Clone, Debug, PartialEq)]
+pub struct PoolState {
+    pub dex: Option<String>,
+    pub pool_id: Option<String>,
+    pub address: String,
+    pub pair: TokenPair,
+    pub a_amount: u64,
+    pub b_amount: u64,
+    pub fee: Option<f64>,
+    pub lp_token: Option<PoolAsset>,
+}
+
+impl PoolState {
+    /// Orders the reserves the same way as the pair keys (ada first, otherwise
+    /// by asset id) so that ratios are consistent across dexs
+    pub fn normalized(self) -> Self {
+        let swap = match (&self.pair.a, &self.pair.b) {
+            (PoolAsset::AssetClass(..), PoolAsset::Ada) => true,
+            (a @ PoolAsset::AssetClass(..), b @ PoolAsset::AssetClass(..)) => {
+                a.to_string() > b.to_string()
+            }
+            _ => false,
+        };
+
+        match swap {
+            true => PoolState {
+                pair: TokenPair {
+                    a: self.pair.b,
+                    b: self.pair.a,
+                },
+                a_amount: self.b_amount,
+                b_amount: self.a_amount,
+                ..self
+            },
+            false => self,
+        }
+    }
+
+    /// Amount of token `b` for each unit of token `a`, not adjusted by decimals
+    pub fn ratio(&self) -> Option<f64> {
+        match self.a_amount {
+            0 => None,
+            x => Some(self.b_amount as f64 / x as f64),
+        }
+    }
+
+    /// Identifies the pool within its pair, pools without an explicit id nor
+    /// an NFT of their own are identified by the address holding the reserves
+    pub fn id(&self) -> &str {
+        self.pool_id.as_deref().unwrap_or(&self.address)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "dex": self.dex,
+            "pool_id": self.pool_id,
+            "address": self.address,
+            "token_a": { "asset": self.pair.a.to_string(), "amount": self.a_amount.to_string() },
+            "token_b": { "asset": self.pair.b.to_string(), "amount": self.b_amount.to_string() },
+            "fee": self.fee,
+            "lp_token": self.lp_token.as_ref().map(|x| x.to_string()),
+        })
+    }
+}
+
 pub type CurrencySymbol = Hash<28>;
 
 pub fn currency_symbol_from(str: &Vec<u8>) -> Option<CurrencySymbol> {
@@ -0,0 +1,78 @@
+use pallas::ledger::primitives::babbage::PlutusData;
+
+use super::{model::PoolAsset, utils::plutus_int};
+
+/// Denominator of the `fee_num` field of Spectrum pools
+const FEE_DENOMINATOR: i128 = 1000;
+
+#[derive(Debug, PartialEq)]
+pub struct SpectrumPoolDatum {
+    pub nft: PoolAsset,
+    pub a: PoolAsset,
+    pub b: PoolAsset,
+    pub lp: PoolAsset,
+    pub fee: f64,
+}
+
+impl TryFrom<&PlutusData> for SpectrumPoolDatum {
+    type Error = ();
+
+    fn try_from(value: &PlutusData) -> Result<Self, Self::Error> {
+        if let PlutusData::Constr(pd) = value {
+            let nft = PoolAsset::try_from(pd.fields.get(0).ok_or(())?)?;
+            let a = PoolAsset::try_from(pd.fields.get(1).ok_or(())?)?;
+            let b = PoolAsset::try_from(pd.fields.get(2).ok_or(())?)?;
+            let lp = PoolAsset::try_from(pd.fields.get(3).ok_or(())?)?;
+            let fee_num = plutus_int(pd.fields.get(4).ok_or(())?).ok_or(())?;
+
+            if !(0..=FEE_DENOMINATOR).contains(&fee_num) {
+                return Err(());
+            }
+
+            // the datum holds the share kept by the pool, not the fee itself
+            return Ok(Self {
+                nft,
+                a,
+                b,
+                lp,
+                fee: (FEE_DENOMINATOR - fee_num) as f64 / FEE_DENOMINATOR as f64,
+            });
+        }
+
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pallas::ledger::primitives::{babbage::PlutusData, Fragment};
+
+    use crate::reducers::liquidity_by_token_pair::{
+        model::PoolAsset, spectrum::SpectrumPoolDatum, utils::pool_asset_from,
+    };
+
+    #[test]
+    fn test_decoding_pool_datum_ada_sun() {
+        let hex_pool_datum = "d8799fd8799f581c7bddf2c27f257eeeef3e892758b479e09c89a73642499797f2a97f3c4b53554e5f4144415f4e4654ffd8799f4040ffd8799f581c9a9693a9a37912a5097918f97918d15240c92ab729a0b7c4aa144d774653554e444145ffd8799f581ce4214b7cce62ac6fbba385d164df48e157eae5863521b5b67ca71d864a53554e5f4144415f4c51ff1903e59f581c00000000000000000000000000000000000000000000000000000000ff00ff";
+        let data = hex::decode(hex_pool_datum).unwrap();
+        let plutus_data = PlutusData::decode_fragment(&data).unwrap();
+        let pool_datum = SpectrumPoolDatum::try_from(&plutus_data).unwrap();
+
+        assert_eq!(PoolAsset::Ada, pool_datum.a);
+
+        let sundae_token = pool_asset_from(
+            &String::from("9a9693a9a37912a5097918f97918d15240c92ab729a0b7c4aa144d77"),
+            &String::from("53554e444145"),
+        )
+        .unwrap();
+        assert_eq!(sundae_token, pool_datum.b);
+
+        let lp_token = pool_asset_from(
+            &String::from("e4214b7cce62ac6fbba385d164df48e157eae5863521b5b67ca71d86"),
+            &String::from("53554e5f4144415f4c51"),
+        )
+        .unwrap();
+        assert_eq!(lp_token, pool_datum.lp);
+        assert_eq!(f64::from(0.003), pool_datum.fee);
+    }
+}
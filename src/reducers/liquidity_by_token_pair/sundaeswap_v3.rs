@@ -0,0 +1,116 @@
+use pallas::ledger::primitives::babbage::PlutusData;
+
+use super::{
+    model::PoolAsset,
+    utils::{plutus_int, pool_asset_from},
+};
+
+/// Denominator of the bid / ask fees of SundaeSwap V3 pools
+const FEE_DENOMINATOR: i128 = 10000;
+
+/// CIP-67 asset name prefix (label 333) of SundaeSwap V3 LP tokens
+pub const LP_TOKEN_PREFIX: [u8; 4] = [0x00, 0x14, 0xdf, 0x10];
+
+#[derive(Debug, PartialEq)]
+pub struct SundaeV3PoolDatum {
+    pub a: PoolAsset,
+    pub b: PoolAsset,
+    pub fee: f64,
+    pub pool_id: String,
+    /// Lovelace held by the pool UTxO on behalf of the protocol, not part of the reserves
+    pub protocol_fees: u64,
+}
+
+impl SundaeV3PoolDatum {
+    /// Asset name of the LP token of this pool, minted under the pool script hash
+    pub fn lp_token_name(&self) -> Option<Vec<u8>> {
+        let ident = hex::decode(&self.pool_id).ok()?;
+        Some([LP_TOKEN_PREFIX.to_vec(), ident].concat())
+    }
+}
+
+/// V3 encodes assets as a `[policy, name]` list rather than a constructor
+fn asset_from_list(value: &PlutusData) -> Option<PoolAsset> {
+    match value {
+        PlutusData::Array(x) => match (x.get(0), x.get(1)) {
+            (Some(PlutusData::BoundedBytes(policy)), Some(PlutusData::BoundedBytes(name))) => {
+                pool_asset_from(&hex::encode(policy.to_vec()), &hex::encode(name.to_vec()))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl TryFrom<&PlutusData> for SundaeV3PoolDatum {
+    type Error = ();
+
+    fn try_from(value: &PlutusData) -> Result<Self, Self::Error> {
+        if let PlutusData::Constr(pd) = value {
+            let pool_id = match pd.fields.get(0) {
+                Some(PlutusData::BoundedBytes(x)) => hex::encode(x.to_vec()),
+                _ => return Err(()),
+            };
+
+            let (a, b) = match pd.fields.get(1) {
+                Some(PlutusData::Array(x)) => (
+                    x.get(0).and_then(asset_from_list).ok_or(())?,
+                    x.get(1).and_then(asset_from_list).ok_or(())?,
+                ),
+                _ => return Err(()),
+            };
+
+            let int_field = |idx: usize| pd.fields.get(idx).and_then(plutus_int).ok_or(());
+
+            // bid and ask fees are usually the same, the bid fee is reported
+            let bid_fee = int_field(3)?;
+            let protocol_fees = u64::try_from(int_field(7)?).map_err(|_| ())?;
+
+            return Ok(Self {
+                a,
+                b,
+                fee: bid_fee as f64 / FEE_DENOMINATOR as f64,
+                pool_id,
+                protocol_fees,
+            });
+        }
+
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pallas::ledger::primitives::{babbage::PlutusData, Fragment};
+
+    use crate::reducers::liquidity_by_token_pair::{
+        model::PoolAsset, sundaeswap_v3::SundaeV3PoolDatum, utils::pool_asset_from,
+    };
+
+    #[test]
+    fn test_decoding_pool_datum_ada_sun() {
+        let hex_pool_datum = "d8799f581cba228444515fbefd2c8725338e49589f206c7f18a33e002b157aac3c9f9f4040ff9f581c9a9693a9a37912a5097918f97918d15240c92ab729a0b7c4aa144d774653554e444145ffff1a000f4240181e181ed87a9fff001a001e8480ff";
+        let data = hex::decode(hex_pool_datum).unwrap();
+        let plutus_data = PlutusData::decode_fragment(&data).unwrap();
+        let pool_datum = SundaeV3PoolDatum::try_from(&plutus_data).unwrap();
+
+        assert_eq!(PoolAsset::Ada, pool_datum.a);
+
+        let sundae_token = pool_asset_from(
+            &String::from("9a9693a9a37912a5097918f97918d15240c92ab729a0b7c4aa144d77"),
+            &String::from("53554e444145"),
+        )
+        .unwrap();
+        assert_eq!(sundae_token, pool_datum.b);
+        assert_eq!(f64::from(0.003), pool_datum.fee);
+        assert_eq!(2000000, pool_datum.protocol_fees);
+        assert_eq!(
+            "ba228444515fbefd2c8725338e49589f206c7f18a33e002b157aac3c",
+            pool_datum.pool_id
+        );
+        assert_eq!(
+            "0014df10ba228444515fbefd2c8725338e49589f206c7f18a33e002b157aac3c",
+            hex::encode(pool_datum.lp_token_name().unwrap())
+        );
+    }
+}
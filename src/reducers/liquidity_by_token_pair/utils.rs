@@ -2,7 +2,7 @@ use lazy_static::__Deref;
 use pallas::{
     codec::utils::CborWrap,
     ledger::{
-        primitives::babbage::{AssetName, DatumOption, PlutusData},
+        primitives::babbage::{AssetName, BigInt, DatumOption, PlutusData},
         traverse::{Asset, MultiEraOutput, MultiEraTx, OriginalHash},
     },
 };
@@ -54,6 +54,42 @@ pub fn resolve_datum(utxo: &MultiEraOutput, tx: &MultiEraTx) -> Result<PlutusDat
     }
 }
 
+/// Renders plutus data using the detailed json schema of cardano-cli
+pub fn plutus_data_to_json(data: &PlutusData) -> serde_json::Value {
+    match data {
+        PlutusData::Constr(x) => {
+            // tags 121-127 and 1280-1400 are the compact encodings of the
+            // constructor index, 102 carries it explicitly
+            let constructor = match x.tag {
+                121..=127 => x.tag - 121,
+                1280..=1400 => x.tag - 1280 + 7,
+                _ => x.any_constructor.unwrap_or_default(),
+            };
+
+            json!({
+                "constructor": constructor,
+                "fields": x.fields.iter().map(plutus_data_to_json).collect::<Vec<_>>(),
+            })
+        }
+        PlutusData::Map(x) => json!({
+            "map": x
+                .iter()
+                .map(|(k, v)| json!({ "k": plutus_data_to_json(k), "v": plutus_data_to_json(v) }))
+                .collect::<Vec<_>>(),
+        }),
+        PlutusData::BigInt(BigInt::Int(x)) => match i64::try_from(i128::from(*x)) {
+            Ok(x) => json!({ "int": x }),
+            Err(_) => json!({ "int": i128::from(*x).to_string() }),
+        },
+        PlutusData::BigInt(BigInt::BigUInt(x)) => json!({ "biguint": hex::encode(x.to_vec()) }),
+        PlutusData::BigInt(BigInt::BigNInt(x)) => json!({ "bignint": hex::encode(x.to_vec()) }),
+        PlutusData::BoundedBytes(x) => json!({ "bytes": hex::encode(x.to_vec()) }),
+        PlutusData::Array(x) => json!({
+            "list": x.iter().map(plutus_data_to_json).collect::<Vec<_>>(),
+        }),
+    }
+}
+
 pub fn serialize_value(
     dex_prefix: &Option<String>,
     a_amount_opt: Option<u64>,
@@ -172,6 +208,13 @@ pub fn get_asset_amount(asset: &PoolAsset, assets: &Vec<Asset>) -> Option<u64> {
     None
 }
 
+pub fn plutus_int(data: &PlutusData) -> Option<i128> {
+    match data {
+        PlutusData::BigInt(BigInt::Int(x)) => Some(i128::from(*x)),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::str::FromStr;
@@ -184,8 +227,8 @@ mod test {
     use crate::reducers::liquidity_by_token_pair::{
         model::{CurrencySymbol, PoolAsset, TokenPair},
         utils::{
-            build_key_value_pair, contains_currency_symbol, get_asset_amount, pool_asset_from,
-            serialize_value,
+            build_key_value_pair, contains_currency_symbol, get_asset_amount, plutus_data_to_json,
+            pool_asset_from, serialize_value,
         },
     };
 
@@ -375,4 +418,18 @@ mod test {
             pool_asset_from(&String::from(CURRENCY_SYMBOL_1), &hex::encode("Tkn2")).unwrap();
         assert_eq!(Some(2), get_asset_amount(&asset, &mock_assets()));
     }
+
+    #[test]
+    fn test_plutus_data_to_json() {
+        let data = hex::decode("d8799f4040ff").unwrap();
+        let plutus_data = PlutusData::decode_fragment(&data).unwrap();
+
+        assert_eq!(
+            plutus_data_to_json(&plutus_data),
+            serde_json::json!({
+                "constructor": 0,
+                "fields": [{ "bytes": "" }, { "bytes": "" }],
+            })
+        );
+    }
 }
@@ -0,0 +1,65 @@
+use pallas::ledger::primitives::babbage::PlutusData;
+
+use super::utils::plutus_int;
+
+/// VyFinance pool datums don't name the assets of the pool, only the amounts
+/// set aside from the reserves (bar fees / treasury) and the issued shares.
+/// The pair is derived from the assets held by the pool UTxO instead.
+#[derive(Debug, PartialEq)]
+pub struct VyFiPoolDatum {
+    pub treasury_a: u64,
+    pub treasury_b: u64,
+    pub issued_shares: u64,
+}
+
+impl TryFrom<&PlutusData> for VyFiPoolDatum {
+    type Error = ();
+
+    fn try_from(value: &PlutusData) -> Result<Self, Self::Error> {
+        if let PlutusData::Constr(pd) = value {
+            if pd.fields.len() != 3 {
+                return Err(());
+            }
+
+            let int_field = |idx: usize| {
+                pd.fields
+                    .get(idx)
+                    .and_then(plutus_int)
+                    .and_then(|x| u64::try_from(x).ok())
+                    .ok_or(())
+            };
+
+            return Ok(Self {
+                treasury_a: int_field(0)?,
+                treasury_b: int_field(1)?,
+                issued_shares: int_field(2)?,
+            });
+        }
+
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pallas::ledger::primitives::{babbage::PlutusData, Fragment};
+
+    use crate::reducers::liquidity_by_token_pair::vyfi::VyFiPoolDatum;
+
+    #[test]
+    fn test_decoding_pool_datum() {
+        let hex_pool_datum = "d8799f1a0016e36018fa1a000182b8ff";
+        let data = hex::decode(hex_pool_datum).unwrap();
+        let plutus_data = PlutusData::decode_fragment(&data).unwrap();
+        let pool_datum = VyFiPoolDatum::try_from(&plutus_data).unwrap();
+
+        assert_eq!(
+            VyFiPoolDatum {
+                treasury_a: 1500000,
+                treasury_b: 250,
+                issued_shares: 99000,
+            },
+            pool_datum
+        );
+    }
+}
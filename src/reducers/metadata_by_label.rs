@@ -0,0 +1,179 @@
+use pallas::ledger::traverse::{MultiEraBlock, MultiEraTx};
+use serde::Deserialize;
+
+use crate::crosscut::metadata::{metadatum_to_json, select_path};
+use crate::{crosscut, model, prelude::*};
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Metadata labels to index (eg: 674 for CIP-20 messages)
+    pub labels: Vec<u64>,
+
+    /// Optional dot-separated path of the sub-field to store (eg: `msg`)
+    ///
+    /// Txs where the path doesn't resolve are still added to the label set,
+    /// but no value is stored for them.
+    pub json_path: Option<String>,
+}
+
+pub struct Reducer {
+    config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+}
+
+impl Reducer {
+    fn config_key(&self, subject: &str) -> String {
+        match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, subject),
+            None => format!("{}.{}", "metadata_by_label".to_string(), subject),
+        }
+    }
+
+    fn tx_commands(&self, tx: &MultiEraTx) -> Vec<model::CRDTCommand> {
+        // metadata of phase-2 failed txs is never applied
+        if !tx.is_valid() {
+            return vec![];
+        }
+
+        let metadata = tx.metadata();
+
+        let metadata = match metadata.as_alonzo() {
+            Some(x) => x,
+            None => return vec![],
+        };
+
+        let tx_hash = tx.hash().to_string();
+        let mut commands = vec![];
+
+        for (label, value) in metadata.iter() {
+            if !self.config.labels.contains(label) {
+                continue;
+            }
+
+            commands.push(model::CRDTCommand::set_add(
+                None,
+                &self.config_key(&label.to_string()),
+                tx_hash.clone(),
+            ));
+
+            let json = metadatum_to_json(value);
+
+            let json = match &self.config.json_path {
+                Some(path) => match select_path(&json, path) {
+                    Some(x) => x.clone(),
+                    None => continue,
+                },
+                None => json,
+            };
+
+            commands.push(model::CRDTCommand::any_write_wins(
+                None,
+                self.config_key(&format!("{}.{}", label, tx_hash)),
+                json,
+            ));
+        }
+
+        commands
+    }
+
+    pub fn reduce_block<'b>(
+        &mut self,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        for tx in ctx.filtered_txs(block).into_iter() {
+            if !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            for crdt in self.tx_commands(&tx) {
+                output.send(gasket::messaging::Message::from(crdt))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
+        let reducer = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+        };
+
+        Ok(super::Reducer::MetadataByLabel(reducer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::ledger::traverse::{Era, MultiEraTx};
+
+    use super::{Config, Reducer};
+    use crate::crosscut::policies::RuntimePolicy;
+    use crate::model::CRDTCommand;
+
+    /// Babbage tx with a CIP-20 message (`{674: {"msg": ["hello"]}}`)
+    const TX: &str = "84a30081825820aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa0001800200a0f5a11902a2a1636d7367816568656c6c6f";
+
+    fn reducer() -> Reducer {
+        Reducer {
+            config: Config {
+                key_prefix: None,
+                filter: None,
+                labels: vec![674],
+                json_path: Some("msg".into()),
+            },
+            filter: None,
+            policy: RuntimePolicy::default(),
+        }
+    }
+
+    #[test]
+    fn labelled_metadata_is_indexed() {
+        let cbor = hex::decode(TX).unwrap();
+        let tx = MultiEraTx::decode(Era::Babbage, &cbor).unwrap();
+        let tx_hash = tx.hash().to_string();
+
+        let commands = reducer().tx_commands(&tx);
+        assert_eq!(commands.len(), 2);
+
+        match &commands[0] {
+            CRDTCommand::SetAdd(key, member) => {
+                assert_eq!(key, "metadata_by_label.674");
+                assert_eq!(member, &tx_hash);
+            }
+            _ => panic!("unexpected command"),
+        }
+
+        match &commands[1] {
+            CRDTCommand::AnyWriteWins(key, _) => {
+                assert_eq!(key, &format!("metadata_by_label.674.{}", tx_hash));
+            }
+            _ => panic!("unexpected command"),
+        }
+    }
+
+    #[test]
+    fn failed_txs_are_skipped() {
+        // flip the is_valid flag that precedes the aux data
+        let cbor = hex::decode(TX.replace("a0f5a1", "a0f4a1")).unwrap();
+        let tx = MultiEraTx::decode(Era::Babbage, &cbor).unwrap();
+
+        assert!(!tx.is_valid());
+        assert!(reducer().tx_commands(&tx).is_empty());
+    }
+}
@@ -1,7 +1,8 @@
 use std::time::Duration;
 
 use gasket::runtime::spawn_stage;
-use pallas::ledger::traverse::MultiEraBlock;
+use pallas::crypto::hash::Hash;
+use pallas::ledger::traverse::{MultiEraBlock, MultiEraTx};
 use serde::Deserialize;
 
 use crate::{bootstrap, crosscut, model};
@@ -11,6 +12,7 @@ type OutputPort = gasket::messaging::OutputPort<model::CRDTCommand>;
 
 pub mod liquidity_by_token_pair;
 pub mod macros;
+pub mod pending_utxo_by_address;
 pub mod point_by_tx;
 pub mod pool_by_stake;
 pub mod utxo_by_address;
@@ -43,6 +45,26 @@ pub mod utxo_by_stake;
 pub mod utxos_by_asset;
 #[cfg(feature = "unstable")]
 pub mod addresses_by_stake;
+#[cfg(feature = "unstable")]
+pub mod balance_by_stake;
+#[cfg(feature = "unstable")]
+pub mod pool_registry;
+#[cfg(feature = "unstable")]
+pub mod delegators_by_pool;
+#[cfg(feature = "unstable")]
+pub mod stake_registration;
+#[cfg(feature = "unstable")]
+pub mod metadata_by_label;
+#[cfg(feature = "unstable")]
+pub mod asset_metadata;
+#[cfg(feature = "unstable")]
+pub mod handle_resolver;
+#[cfg(feature = "unstable")]
+pub mod witness_store;
+#[cfg(feature = "unstable")]
+pub mod dex_swaps;
+#[cfg(feature = "unstable")]
+pub mod chain_stats;
 
 #[derive(Deserialize)]
 #[serde(tag = "type")]
@@ -51,6 +73,7 @@ pub enum Config {
     UtxoByAddress(utxo_by_address::Config),
     PointByTx(point_by_tx::Config),
     PoolByStake(pool_by_stake::Config),
+    PendingUtxoByAddress(pending_utxo_by_address::Config),
 
     #[cfg(feature = "unstable")]
     AddressByTxo(address_by_txo::Config),
@@ -78,6 +101,26 @@ pub enum Config {
     SupplyByAsset(supply_by_asset::Config),
     #[cfg(feature = "unstable")]
     AddressesByStake(addresses_by_stake::Config),
+    #[cfg(feature = "unstable")]
+    BalanceByStake(balance_by_stake::Config),
+    #[cfg(feature = "unstable")]
+    PoolRegistry(pool_registry::Config),
+    #[cfg(feature = "unstable")]
+    DelegatorsByPool(delegators_by_pool::Config),
+    #[cfg(feature = "unstable")]
+    StakeRegistration(stake_registration::Config),
+    #[cfg(feature = "unstable")]
+    MetadataByLabel(metadata_by_label::Config),
+    #[cfg(feature = "unstable")]
+    AssetMetadata(asset_metadata::Config),
+    #[cfg(feature = "unstable")]
+    HandleResolver(handle_resolver::Config),
+    #[cfg(feature = "unstable")]
+    WitnessStore(witness_store::Config),
+    #[cfg(feature = "unstable")]
+    DexSwaps(dex_swaps::Config),
+    #[cfg(feature = "unstable")]
+    ChainStats(chain_stats::Config),
 }
 
 impl Config {
@@ -85,40 +128,63 @@ impl Config {
         self,
         chain: &crosscut::ChainWellKnownInfo,
         policy: &crosscut::policies::RuntimePolicy,
-    ) -> Reducer {
-        match self {
-            Config::LiquidityByTokenPair(c) => c.plugin(policy),
-            Config::UtxoByAddress(c) => c.plugin(policy),
-            Config::PointByTx(c) => c.plugin(),
-            Config::PoolByStake(c) => c.plugin(),
+    ) -> Result<Reducer, crate::Error> {
+        let reducer = match self {
+            Config::LiquidityByTokenPair(c) => c.plugin(chain, policy)?,
+            Config::UtxoByAddress(c) => c.plugin(chain, policy)?,
+            Config::PointByTx(c) => c.plugin(chain, policy)?,
+            Config::PoolByStake(c) => c.plugin(chain, policy)?,
+            Config::PendingUtxoByAddress(c) => c.plugin(),
 
             #[cfg(feature = "unstable")]
-            Config::AddressByTxo(c) => c.plugin(policy),
+            Config::AddressByTxo(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::BalanceByAddress(c) => c.plugin(policy),
+            Config::BalanceByAddress(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::TxByHash(c) => c.plugin(chain, policy),
+            Config::TxByHash(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::TxCountByAddress(c) => c.plugin(policy),
+            Config::TxCountByAddress(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::BlockHeaderByHash(c) => c.plugin(policy),
+            Config::BlockHeaderByHash(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::AddressByAsset(c) => c.plugin(),
+            Config::AddressByAsset(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::LastBlockParameters(c) => c.plugin(chain),
+            Config::LastBlockParameters(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::TxCountByNativeTokenPolicyId(c) => c.plugin(chain),
+            Config::TxCountByNativeTokenPolicyId(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::AssetHoldersByAsset(c) => c.plugin(chain, policy),
+            Config::AssetHoldersByAsset(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::UtxosByAsset(c) => c.plugin(policy),
+            Config::UtxosByAsset(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::UtxoByStake(c) => c.plugin(policy),
+            Config::UtxoByStake(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::SupplyByAsset(c) => c.plugin(policy),
+            Config::SupplyByAsset(c) => c.plugin(chain, policy)?,
             #[cfg(feature = "unstable")]
-            Config::AddressesByStake(c) => c.plugin(policy),
-        }
+            Config::AddressesByStake(c) => c.plugin(chain, policy)?,
+            #[cfg(feature = "unstable")]
+            Config::BalanceByStake(c) => c.plugin(chain, policy)?,
+            #[cfg(feature = "unstable")]
+            Config::PoolRegistry(c) => c.plugin(chain, policy)?,
+            #[cfg(feature = "unstable")]
+            Config::DelegatorsByPool(c) => c.plugin(chain, policy)?,
+            #[cfg(feature = "unstable")]
+            Config::StakeRegistration(c) => c.plugin(chain, policy)?,
+            #[cfg(feature = "unstable")]
+            Config::MetadataByLabel(c) => c.plugin(chain, policy)?,
+            #[cfg(feature = "unstable")]
+            Config::AssetMetadata(c) => c.plugin(chain, policy)?,
+            #[cfg(feature = "unstable")]
+            Config::HandleResolver(c) => c.plugin(chain, policy)?,
+            #[cfg(feature = "unstable")]
+            Config::WitnessStore(c) => c.plugin(chain, policy)?,
+            #[cfg(feature = "unstable")]
+            Config::DexSwaps(c) => c.plugin(chain, policy)?,
+            #[cfg(feature = "unstable")]
+            Config::ChainStats(c) => c.plugin(chain, policy)?,
+        };
+
+        Ok(reducer)
     }
 }
 
@@ -134,16 +200,18 @@ impl Bootstrapper {
         configs: Vec<Config>,
         chain: &crosscut::ChainWellKnownInfo,
         policy: &crosscut::policies::RuntimePolicy,
-    ) -> Self {
-        Self {
-            reducers: configs
-                .into_iter()
-                .map(|x| x.plugin(chain, policy))
-                .collect(),
+    ) -> Result<Self, crate::Error> {
+        let reducers = configs
+            .into_iter()
+            .map(|x| x.plugin(chain, policy))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            reducers,
             input: Default::default(),
             output: Default::default(),
             policy: policy.clone(),
-        }
+        })
     }
 
     pub fn borrow_input_port(&mut self) -> &'_ mut InputPort {
@@ -172,6 +240,7 @@ pub enum Reducer {
     UtxoByAddress(utxo_by_address::Reducer),
     PointByTx(point_by_tx::Reducer),
     PoolByStake(pool_by_stake::Reducer),
+    PendingUtxoByAddress(pending_utxo_by_address::Reducer),
 
     #[cfg(feature = "unstable")]
     AddressByTxo(address_by_txo::Reducer),
@@ -199,6 +268,26 @@ pub enum Reducer {
     SupplyByAsset(supply_by_asset::Reducer),
     #[cfg(feature = "unstable")]
     AddressesByStake(addresses_by_stake::Reducer),
+    #[cfg(feature = "unstable")]
+    BalanceByStake(balance_by_stake::Reducer),
+    #[cfg(feature = "unstable")]
+    PoolRegistry(pool_registry::Reducer),
+    #[cfg(feature = "unstable")]
+    DelegatorsByPool(delegators_by_pool::Reducer),
+    #[cfg(feature = "unstable")]
+    StakeRegistration(stake_registration::Reducer),
+    #[cfg(feature = "unstable")]
+    MetadataByLabel(metadata_by_label::Reducer),
+    #[cfg(feature = "unstable")]
+    AssetMetadata(asset_metadata::Reducer),
+    #[cfg(feature = "unstable")]
+    HandleResolver(handle_resolver::Reducer),
+    #[cfg(feature = "unstable")]
+    WitnessStore(witness_store::Reducer),
+    #[cfg(feature = "unstable")]
+    DexSwaps(dex_swaps::Reducer),
+    #[cfg(feature = "unstable")]
+    ChainStats(chain_stats::Reducer),
 }
 
 impl Reducer {
@@ -211,8 +300,10 @@ impl Reducer {
         match self {
             Reducer::LiquidityByTokenPair(x) => x.reduce_block(block, ctx, output),
             Reducer::UtxoByAddress(x) => x.reduce_block(block, ctx, output),
-            Reducer::PointByTx(x) => x.reduce_block(block, output),
-            Reducer::PoolByStake(x) => x.reduce_block(block, output),
+            Reducer::PointByTx(x) => x.reduce_block(block, ctx, output),
+            Reducer::PoolByStake(x) => x.reduce_block(block, ctx, output),
+            // pending txs are only tracked while in the mempool
+            Reducer::PendingUtxoByAddress(_) => Ok(()),
 
             #[cfg(feature = "unstable")]
             Reducer::AddressByTxo(x) => x.reduce_block(block, ctx, output),
@@ -227,9 +318,9 @@ impl Reducer {
             #[cfg(feature = "unstable")]
             Reducer::AddressByAsset(x) => x.reduce_block(block, ctx, output),
             #[cfg(feature = "unstable")]
-            Reducer::LastBlockParameters(x) => x.reduce_block(block, output),
+            Reducer::LastBlockParameters(x) => x.reduce_block(block, ctx, output),
             #[cfg(feature = "unstable")]
-            Reducer::TxCountByNativeTokenPolicyId(x) => x.reduce_block(block, output),
+            Reducer::TxCountByNativeTokenPolicyId(x) => x.reduce_block(block, ctx, output),
             #[cfg(feature = "unstable")]
             Reducer::AssetHoldersByAssetId(x) => x.reduce_block(block, ctx, output),
             #[cfg(feature = "unstable")]
@@ -240,6 +331,49 @@ impl Reducer {
             Reducer::SupplyByAsset(x) => x.reduce_block(block, ctx, output),
             #[cfg(feature = "unstable")]
             Reducer::AddressesByStake(x) => x.reduce_block(block, ctx, output),
+            #[cfg(feature = "unstable")]
+            Reducer::BalanceByStake(x) => x.reduce_block(block, ctx, output),
+            #[cfg(feature = "unstable")]
+            Reducer::PoolRegistry(x) => x.reduce_block(block, ctx, output),
+            #[cfg(feature = "unstable")]
+            Reducer::DelegatorsByPool(x) => x.reduce_block(block, ctx, output),
+            #[cfg(feature = "unstable")]
+            Reducer::StakeRegistration(x) => x.reduce_block(block, ctx, output),
+            #[cfg(feature = "unstable")]
+            Reducer::MetadataByLabel(x) => x.reduce_block(block, ctx, output),
+            #[cfg(feature = "unstable")]
+            Reducer::AssetMetadata(x) => x.reduce_block(block, ctx, output),
+            #[cfg(feature = "unstable")]
+            Reducer::HandleResolver(x) => x.reduce_block(block, ctx, output),
+            #[cfg(feature = "unstable")]
+            Reducer::WitnessStore(x) => x.reduce_block(block, ctx, output),
+            #[cfg(feature = "unstable")]
+            Reducer::DexSwaps(x) => x.reduce_block(block, ctx, output),
+            #[cfg(feature = "unstable")]
+            Reducer::ChainStats(x) => x.reduce_block(block, ctx, output),
+        }
+    }
+
+    pub fn reduce_mempool_add(
+        &mut self,
+        tx: &MultiEraTx,
+        output: &mut OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        match self {
+            Reducer::PendingUtxoByAddress(x) => x.reduce_mempool_add(tx, output),
+            // reducers that only care about confirmed blocks ignore the mempool
+            _ => Ok(()),
+        }
+    }
+
+    pub fn reduce_mempool_remove(
+        &mut self,
+        tx_hash: &Hash<32>,
+        output: &mut OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        match self {
+            Reducer::PendingUtxoByAddress(x) => x.reduce_mempool_remove(tx_hash, output),
+            _ => Ok(()),
         }
     }
 }
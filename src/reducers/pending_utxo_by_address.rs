@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+
+use pallas::crypto::hash::Hash;
+use pallas::ledger::traverse::MultiEraTx;
+use serde::Deserialize;
+
+use crate::{model, prelude::*};
+
+/// Indexes the outputs produced by txs that are still waiting in the mempool
+///
+/// This reducer only reacts to payloads coming from the `N2CMempool` source.
+/// Entries are removed once the tx leaves the mempool. Since the in-memory
+/// record of pending entries doesn't survive a restart, every key is also
+/// bounded by a TTL so that stale entries eventually go away.
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub ttl_secs: Option<u64>,
+}
+
+pub struct Reducer {
+    config: Config,
+    pending: HashMap<Hash<32>, Vec<(String, String)>>,
+}
+
+impl Reducer {
+    fn key_prefix(&self) -> &str {
+        self.config
+            .key_prefix
+            .as_deref()
+            .unwrap_or("pending_utxo_by_address")
+    }
+
+    pub fn reduce_mempool_add(
+        &mut self,
+        tx: &MultiEraTx,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let tx_hash = tx.hash();
+        let ttl = self.config.ttl_secs.unwrap_or(3600);
+        let mut entries = Vec::new();
+
+        for (idx, produced) in tx.produces() {
+            let address = produced.address().map(|x| x.to_string()).or_panic()?;
+            let member = format!("{}#{}", tx_hash, idx);
+
+            let crdt =
+                model::CRDTCommand::set_add(Some(self.key_prefix()), &address, member.clone());
+            output.send(crdt.into())?;
+
+            let crdt = model::CRDTCommand::expire(Some(self.key_prefix()), &address, ttl);
+            output.send(crdt.into())?;
+
+            entries.push((address, member));
+        }
+
+        self.pending.insert(tx_hash, entries);
+
+        Ok(())
+    }
+
+    pub fn reduce_mempool_remove(
+        &mut self,
+        tx_hash: &Hash<32>,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let entries = match self.pending.remove(tx_hash) {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        for (address, member) in entries {
+            let crdt = model::CRDTCommand::set_remove(Some(self.key_prefix()), &address, member);
+            output.send(crdt.into())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn plugin(self) -> super::Reducer {
+        let reducer = Reducer {
+            config: self,
+            pending: HashMap::new(),
+        };
+
+        super::Reducer::PendingUtxoByAddress(reducer)
+    }
+}
@@ -2,15 +2,19 @@ use pallas::crypto::hash::Hash;
 use pallas::ledger::traverse::MultiEraBlock;
 use serde::Deserialize;
 
-use crate::model;
+use crate::prelude::*;
+use crate::{crosscut, model};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
 }
 
 impl Reducer {
@@ -37,13 +41,16 @@ impl Reducer {
     pub fn reduce_block<'b>(
         &mut self,
         block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
         let block_hash = block.hash();
         let block_slot = block.slot();
 
-        for tx in &block.txs() {
-            self.send_set_add(tx.hash(), block_slot, block_hash, output)?;
+        for tx in &ctx.filtered_txs(block) {
+            if filter_matches!(self, block, &tx, ctx) {
+                self.send_set_add(tx.hash(), block_slot, block_hash, output)?;
+            }
         }
 
         Ok(())
@@ -51,8 +58,19 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self) -> super::Reducer {
-        let worker = Reducer { config: self };
-        super::Reducer::PointByTx(worker)
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
+        let worker = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+        };
+
+        Ok(super::Reducer::PointByTx(worker))
     }
 }
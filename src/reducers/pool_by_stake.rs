@@ -3,15 +3,19 @@ use pallas::ledger::primitives::alonzo::{PoolKeyhash, StakeCredential};
 use pallas::ledger::traverse::MultiEraBlock;
 use serde::Deserialize;
 
-use crate::model;
+use crate::prelude::*;
+use crate::{crosscut, model};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
 }
 
 impl Reducer {
@@ -44,12 +48,13 @@ impl Reducer {
     pub fn reduce_block<'b>(
         &mut self,
         block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
         let slot = block.slot();
 
-        for tx in block.txs() {
-            if tx.is_valid() {
+        for tx in ctx.filtered_txs(block) {
+            if tx.is_valid() && filter_matches!(self, block, &tx, ctx) {
                 for cert in tx.certs() {
                     if let Some(cert) = cert.as_alonzo() {
                         if let alonzo::Certificate::StakeDelegation(cred, pool) = cert {
@@ -65,8 +70,19 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self) -> super::Reducer {
-        let reducer = Reducer { config: self };
-        super::Reducer::PoolByStake(reducer)
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
+        let reducer = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+        };
+
+        Ok(super::Reducer::PoolByStake(reducer))
     }
 }
@@ -0,0 +1,236 @@
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use pallas::codec::utils::Nullable;
+use pallas::ledger::addresses::Address;
+use pallas::ledger::primitives::alonzo::{self, PoolKeyhash, Relay};
+use pallas::ledger::traverse::MultiEraBlock;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::crosscut::epochs::block_epoch;
+use crate::prelude::*;
+use crate::{crosscut, model};
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+}
+
+/// Score of the pools in the `active` sorted set that have no pending
+/// retirement
+const NOT_RETIRING: u64 = u64::MAX;
+
+pub struct Reducer {
+    config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+    chain: crosscut::ChainWellKnownInfo,
+}
+
+fn nullable_to_json<T, F>(value: &Nullable<T>, f: F) -> serde_json::Value
+where
+    T: Clone,
+    F: Fn(&T) -> serde_json::Value,
+{
+    match value {
+        Nullable::Some(x) => f(x),
+        _ => serde_json::Value::Null,
+    }
+}
+
+fn relay_to_json(relay: &Relay) -> serde_json::Value {
+    match relay {
+        Relay::SingleHostAddr(port, ipv4, ipv6) => json!({
+            "type": "single_host_addr",
+            "port": nullable_to_json(port, |x| json!(x)),
+            "ipv4": nullable_to_json(ipv4, |x| match <[u8; 4]>::try_from(x.as_slice()) {
+                Ok(x) => json!(Ipv4Addr::from(x).to_string()),
+                Err(_) => json!(hex::encode(x.as_slice())),
+            }),
+            "ipv6": nullable_to_json(ipv6, |x| match <[u8; 16]>::try_from(x.as_slice()) {
+                Ok(x) => json!(Ipv6Addr::from(x).to_string()),
+                Err(_) => json!(hex::encode(x.as_slice())),
+            }),
+        }),
+        Relay::SingleHostName(port, dns) => json!({
+            "type": "single_host_name",
+            "port": nullable_to_json(port, |x| json!(x)),
+            "dns": dns,
+        }),
+        Relay::MultiHostName(dns) => json!({
+            "type": "multi_host_name",
+            "dns": dns,
+        }),
+    }
+}
+
+fn reward_account_to_string(bytes: &[u8]) -> String {
+    match Address::from_bytes(bytes) {
+        Ok(Address::Stake(x)) => x.to_bech32().unwrap_or_else(|_| hex::encode(bytes)),
+        _ => hex::encode(bytes),
+    }
+}
+
+impl Reducer {
+    fn config_key(&self, subject: &str) -> String {
+        match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, subject),
+            None => format!("{}.{}", "pool_registry".to_string(), subject),
+        }
+    }
+
+    fn process_registration(
+        &mut self,
+        cert: &alonzo::Certificate,
+        slot: u64,
+        epoch: u64,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        if let alonzo::Certificate::PoolRegistration {
+            operator,
+            vrf_keyhash,
+            pledge,
+            cost,
+            margin,
+            reward_account,
+            pool_owners,
+            relays,
+            pool_metadata,
+        } = cert
+        {
+            let pool_id = operator.to_string();
+
+            let value = json!({
+                "pool_id": pool_id,
+                "vrf_keyhash": vrf_keyhash.to_string(),
+                "pledge": pledge,
+                "cost": cost,
+                "margin": {
+                    "numerator": margin.numerator,
+                    "denominator": margin.denominator,
+                },
+                "reward_account": reward_account_to_string(reward_account),
+                "owners": pool_owners.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+                "relays": relays.iter().map(relay_to_json).collect::<Vec<_>>(),
+                "metadata": nullable_to_json(pool_metadata, |x| json!({
+                    "url": x.url,
+                    "hash": x.hash.to_string(),
+                })),
+                "registered_slot": slot,
+                "registered_epoch": epoch,
+            });
+
+            let crdt =
+                model::CRDTCommand::last_write_wins(None, &self.config_key(&pool_id), value, slot);
+            output.send(gasket::messaging::Message::from(crdt))?;
+
+            let crdt = model::CRDTCommand::set_add(
+                None,
+                &self.config_key(&format!("registered.{}", epoch)),
+                pool_id.clone(),
+            );
+            output.send(gasket::messaging::Message::from(crdt))?;
+
+            // a registration cancels any pending retirement of the pool
+            let crdt = model::CRDTCommand::delete(
+                None,
+                self.config_key(&format!("{}.retirement", pool_id)),
+            );
+            output.send(gasket::messaging::Message::from(crdt))?;
+
+            self.send_active(pool_id, NOT_RETIRING, output)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_retirement(
+        &mut self,
+        pool: &PoolKeyhash,
+        retiring_epoch: u64,
+        slot: u64,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let pool_id = pool.to_string();
+
+        let crdt = model::CRDTCommand::last_write_wins(
+            None,
+            &self.config_key(&format!("{}.retirement", pool_id)),
+            retiring_epoch.to_string(),
+            slot,
+        );
+        output.send(gasket::messaging::Message::from(crdt))?;
+
+        self.send_active(pool_id, retiring_epoch, output)
+    }
+
+    /// Pools are kept in the `active` sorted set scored by the epoch they
+    /// retire at, so the pools active in epoch `e` are the ones scored above
+    /// `e` and the ones retiring at `e` are the ones scored exactly `e`. Each
+    /// cert overwrites the score, which keeps replays idempotent.
+    fn send_active(
+        &mut self,
+        pool_id: String,
+        retiring_epoch: u64,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let crdt = model::CRDTCommand::last_write_wins(
+            None,
+            &self.config_key("active"),
+            pool_id,
+            retiring_epoch,
+        );
+
+        output.send(gasket::messaging::Message::from(crdt))
+    }
+
+    pub fn reduce_block<'b>(
+        &mut self,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let slot = block.slot();
+        let epoch = block_epoch(&self.chain, block);
+
+        for tx in ctx.filtered_txs(block) {
+            if !tx.is_valid() || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            for cert in tx.certs() {
+                match cert.as_alonzo() {
+                    Some(x @ alonzo::Certificate::PoolRegistration { .. }) => {
+                        self.process_registration(x, slot, epoch, output)?;
+                    }
+                    Some(alonzo::Certificate::PoolRetirement(pool, retiring_epoch)) => {
+                        self.process_retirement(pool, *retiring_epoch, slot, output)?;
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
+        let reducer = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+            chain: chain.clone(),
+        };
+
+        Ok(super::Reducer::PoolRegistry(reducer))
+    }
+}
@@ -0,0 +1,207 @@
+use pallas::ledger::primitives::alonzo::{self, StakeCredential};
+use pallas::ledger::traverse::{MultiEraBlock, MultiEraTx};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::crosscut::addresses::stake_credential_to_string;
+use crate::prelude::*;
+use crate::{crosscut, model};
+
+/// Registration state of each stake credential, as a last-write-wins entry
+/// under `<prefix>.<credential hex>`
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Deposit (in lovelace) assumed for stake registrations, defaults to the
+    /// mainnet `keyDeposit` protocol parameter
+    ///
+    /// Pre-Conway certs don't carry their deposit and protocol params aren't
+    /// tracked, so it's reported as `assumed_deposit`.
+    pub assumed_key_deposit: Option<u64>,
+}
+
+pub struct Reducer {
+    config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+    assumed_key_deposit: u64,
+}
+
+impl Reducer {
+    fn config_key(&self, subject: &str) -> String {
+        match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, subject),
+            None => format!("{}.{}", "stake_registration".to_string(), subject),
+        }
+    }
+
+    fn state_command(
+        &self,
+        cred: &StakeCredential,
+        registered: bool,
+        tx_hash: String,
+        slot: u64,
+    ) -> model::CRDTCommand {
+        let value = json!({
+            "state": if registered { "registered" } else { "deregistered" },
+            "assumed_deposit": self.assumed_key_deposit,
+            "slot": slot,
+            "tx_hash": tx_hash,
+        });
+
+        model::CRDTCommand::last_write_wins(
+            None,
+            &self.config_key(&stake_credential_to_string(cred)),
+            value,
+            slot,
+        )
+    }
+
+    fn tx_commands(&self, tx: &MultiEraTx, slot: u64) -> Vec<model::CRDTCommand> {
+        // certs of phase-2 failed txs are never applied
+        if !tx.is_valid() {
+            return vec![];
+        }
+
+        let mut commands = vec![];
+
+        // TODO: Conway registration certs carry an explicit deposit, handle them
+        // once pallas exposes the new certificate variants
+        for cert in tx.certs() {
+            match cert.as_alonzo() {
+                Some(alonzo::Certificate::StakeRegistration(cred)) => {
+                    commands.push(self.state_command(cred, true, tx.hash().to_string(), slot));
+                }
+                Some(alonzo::Certificate::StakeDeregistration(cred)) => {
+                    commands.push(self.state_command(cred, false, tx.hash().to_string(), slot));
+                }
+                _ => (),
+            }
+        }
+
+        commands
+    }
+
+    pub fn reduce_block<'b>(
+        &mut self,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let slot = block.slot();
+
+        for tx in ctx.filtered_txs(block) {
+            if !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            for crdt in self.tx_commands(&tx, slot) {
+                output.send(gasket::messaging::Message::from(crdt))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+        let assumed_key_deposit = self.assumed_key_deposit.unwrap_or(2_000_000);
+
+        let reducer = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+            assumed_key_deposit,
+        };
+
+        Ok(super::Reducer::StakeRegistration(reducer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pallas::ledger::traverse::{Era, MultiEraTx};
+    use serde_json::json;
+
+    use super::{Config, Reducer};
+    use crate::crosscut::policies::RuntimePolicy;
+    use crate::model::{CRDTCommand, Value};
+
+    /// Babbage tx registering key hash `11..11` and deregistering script hash
+    /// `22..22`
+    const TX: &str = "84a40081825820aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa0001800200048282008200581c1111111111111111111111111111111111111111111111111111111182018201581c22222222222222222222222222222222222222222222222222222222a0f5f6";
+
+    fn reducer() -> Reducer {
+        Reducer {
+            config: Config {
+                key_prefix: None,
+                filter: None,
+                assumed_key_deposit: None,
+            },
+            filter: None,
+            policy: RuntimePolicy::default(),
+            assumed_key_deposit: 2_000_000,
+        }
+    }
+
+    fn states(commands: Vec<CRDTCommand>) -> Vec<(String, serde_json::Value)> {
+        commands
+            .into_iter()
+            .map(|x| match x {
+                CRDTCommand::LastWriteWins(key, Value::Json(value), 42) => (key, value),
+                _ => panic!("unexpected command"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn certs_set_the_state_of_their_credential() {
+        let cbor = hex::decode(TX).unwrap();
+        let tx = MultiEraTx::decode(Era::Babbage, &cbor).unwrap();
+        let tx_hash = tx.hash().to_string();
+
+        assert_eq!(
+            states(reducer().tx_commands(&tx, 42)),
+            vec![
+                (
+                    format!("stake_registration.{}", "11".repeat(28)),
+                    json!({
+                        "state": "registered",
+                        "assumed_deposit": 2_000_000,
+                        "slot": 42,
+                        "tx_hash": tx_hash,
+                    })
+                ),
+                (
+                    format!("stake_registration.{}", "22".repeat(28)),
+                    json!({
+                        "state": "deregistered",
+                        "assumed_deposit": 2_000_000,
+                        "slot": 42,
+                        "tx_hash": tx_hash,
+                    })
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn failed_txs_are_skipped() {
+        let mut cbor = hex::decode(TX).unwrap();
+
+        // flip the is_valid flag that precedes the empty aux data
+        let flag = cbor.len() - 2;
+        cbor[flag] = 0xf4;
+
+        let tx = MultiEraTx::decode(Era::Babbage, &cbor).unwrap();
+        assert!(reducer().tx_commands(&tx, 42).is_empty());
+    }
+}
@@ -1,23 +1,30 @@
 use std::str::FromStr;
 
-use gasket::error::AsWorkError;
 use pallas::crypto::hash::Hash;
 use pallas::ledger::traverse::Asset;
 use pallas::ledger::traverse::MultiEraBlock;
 use serde::Deserialize;
 
+use crate::crosscut::aggregation::{bucket_key, AggrType, Aggregator};
+use crate::prelude::*;
 use crate::{crosscut, model};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
     pub policy_ids_hex: Option<Vec<String>>,
+
+    /// Keep the minted / burned amount per bucket instead of the total supply
+    pub aggr_by: Option<AggrType>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
     policy_ids: Option<Vec<Hash<28>>>,
+    aggregator: Aggregator,
 }
 
 impl Reducer {
@@ -33,6 +40,7 @@ impl Reducer {
         policy: &Hash<28>,
         asset: &Vec<u8>,
         qty: i64,
+        bucket: Option<&str>,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
         if !self.is_policy_id_accepted(&policy) {
@@ -46,6 +54,8 @@ impl Reducer {
             None => format!("{}.{}", "supply_by_asset".to_string(), asset_id),
         };
 
+        let key = bucket_key(key, bucket);
+
         let crdt = model::CRDTCommand::PNCounter(key, qty);
 
         output.send(crdt.into())
@@ -57,11 +67,17 @@ impl Reducer {
         ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        for tx in block.txs().into_iter() {
+        let bucket = self.aggregator.bucket(block);
+
+        for tx in ctx.filtered_txs(block).into_iter() {
+            if !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
             if let Some(mints) = tx.mint().as_alonzo() {
                 for (policy, assets) in mints.iter() {
                     for (name, amount) in assets.iter() {
-                        self.process_asset(policy, name, *amount, output)?;
+                        self.process_asset(policy, name, *amount, bucket.as_deref(), output)?;
                     }
                 }
             }
@@ -72,7 +88,13 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self, policy: &crosscut::policies::RuntimePolicy) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
         let policy_ids: Option<Vec<Hash<28>>> = match &self.policy_ids_hex {
             Some(pids) => {
                 let ps = pids
@@ -85,12 +107,16 @@ impl Config {
             None => None,
         };
 
+        let aggregator = Aggregator::new(self.aggr_by, chain);
+
         let reducer = Reducer {
             config: self,
+            filter,
             policy: policy.clone(),
             policy_ids,
+            aggregator,
         };
 
-        super::Reducer::SupplyByAsset(reducer)
+        Ok(super::Reducer::SupplyByAsset(reducer))
     }
 }
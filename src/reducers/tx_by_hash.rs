@@ -20,12 +20,13 @@ impl Default for Projection {
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
-    pub filter: Option<crosscut::filters::Predicate>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
     pub projection: Option<Projection>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
     time: crosscut::time::NaiveProvider,
 }
@@ -63,7 +64,7 @@ impl Reducer {
         ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        for tx in &block.txs() {
+        for tx in &ctx.filtered_txs(block) {
             if filter_matches!(self, block, &tx, ctx) {
                 self.send(block, tx, output)?;
             }
@@ -78,12 +79,15 @@ impl Config {
         self,
         chain: &crosscut::ChainWellKnownInfo,
         policy: &crosscut::policies::RuntimePolicy,
-    ) -> super::Reducer {
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
         let worker = Reducer {
             config: self,
+            filter,
             policy: policy.clone(),
             time: crosscut::time::NaiveProvider::new(chain.clone()),
         };
-        super::Reducer::TxByHash(worker)
+        Ok(super::Reducer::TxByHash(worker))
     }
 }
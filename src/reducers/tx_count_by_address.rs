@@ -3,17 +3,21 @@ use pallas::ledger::traverse::{MultiEraBlock, OutputRef};
 use serde::Deserialize;
 use std::collections::HashSet;
 
+use crate::crosscut::aggregation::{bucket_key, AggrType, Aggregator};
 use crate::{crosscut, model, prelude::*};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
-    pub filter: Option<crosscut::filters::Predicate>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+    pub aggr_by: Option<AggrType>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
+    aggregator: Aggregator,
 }
 
 impl Reducer {
@@ -22,6 +26,7 @@ impl Reducer {
         ctx: &model::BlockContext,
         input: &OutputRef,
         seen: &mut HashSet<String>,
+        bucket: Option<&str>,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
         let utxo = ctx.find_utxo(input).apply_policy(&self.policy).or_panic()?;
@@ -38,6 +43,8 @@ impl Reducer {
                 Some(prefix) => format!("{}.{}", prefix, address),
                 None => format!("{}.{}", "txcount_by_address".to_string(), address),
             };
+
+            let key = bucket_key(key, bucket);
     
             let crdt = model::CRDTCommand::PNCounter(key, 1);
     
@@ -51,6 +58,7 @@ impl Reducer {
         &mut self,
         tx_output: &MultiEraOutput,
         seen: &mut HashSet<String>,
+        bucket: Option<&str>,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
         let address = tx_output.address().map(|x| x.to_string()).or_panic()?;
@@ -60,6 +68,8 @@ impl Reducer {
                 Some(prefix) => format!("{}.{}", prefix, address),
                 None => format!("{}.{}", "txcount_by_address".to_string(), address),
             };
+
+            let key = bucket_key(key, bucket);
     
             let crdt = model::CRDTCommand::PNCounter(key, 1);
     
@@ -75,16 +85,29 @@ impl Reducer {
         ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        for tx in block.txs().into_iter() {
+        let bucket = self.aggregator.bucket(block);
+
+        for tx in ctx.filtered_txs(block).into_iter() {
             if filter_matches!(self, block, &tx, ctx) {
                 let mut seen = HashSet::new();
                 
                 for input in tx.inputs().iter().map(|i| i.output_ref()) {
-                    self.process_inbound_txo(&ctx, &input, &mut seen, output)?;
+                    self.process_inbound_txo(
+                        &ctx,
+                        &input,
+                        &mut seen,
+                        bucket.as_deref(),
+                        output,
+                    )?;
                 }
 
                 for (_idx, tx_output) in tx.outputs().iter().enumerate() {
-                    self.process_outbound_txo(tx_output, &mut seen, output)?;
+                    self.process_outbound_txo(
+                        tx_output,
+                        &mut seen,
+                        bucket.as_deref(),
+                        output,
+                    )?;
                 }
             }
         }
@@ -94,12 +117,21 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self, policy: &crosscut::policies::RuntimePolicy) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+        let aggregator = Aggregator::new(self.aggr_by, chain);
+
         let reducer = Reducer {
             config: self,
+            filter,
             policy: policy.clone(),
+            aggregator,
         };
 
-        super::Reducer::TxCountByAddress(reducer)
+        Ok(super::Reducer::TxCountByAddress(reducer))
     }
 }
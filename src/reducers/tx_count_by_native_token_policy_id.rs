@@ -2,60 +2,48 @@ use serde::Deserialize;
 
 use pallas::ledger::traverse::{Feature, MultiEraBlock};
 
-use crate::crosscut::epochs::block_epoch;
+use crate::crosscut::aggregation::{bucket_key, AggrType, Aggregator};
+use crate::prelude::*;
 use crate::{crosscut, model};
 
-#[derive(Deserialize, Copy, Clone)]
-pub enum AggrType {
-    Epoch,
-}
-
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
     pub aggr_by: Option<AggrType>,
 }
 
 pub struct Reducer {
     config: Config,
-    chain: crosscut::ChainWellKnownInfo,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+    aggregator: Aggregator,
 }
 
 impl Reducer {
-    fn config_key(&self, policy_id: String, epoch_no: u64) -> String {
+    fn config_key(&self, policy_id: String, bucket: Option<&str>) -> String {
         let def_key_prefix = "transaction_count_by_native_token_policy";
 
-        match &self.config.aggr_by {
-            Some(aggr_type) => {
-                match aggr_type {
-                    AggrType::Epoch => {
-                        return match &self.config.key_prefix {
-                            Some(prefix) => format!("{}.{}.{}", prefix, policy_id, epoch_no),
-                            None => format!("{}.{}", def_key_prefix.to_string(), policy_id),
-                        };
-                    }
-                }
-            },
-            None => {
-                return match &self.config.key_prefix {
-                    Some(prefix) => format!("{}.{}", prefix, policy_id),
-                    None => format!("{}.{}", def_key_prefix.to_string(), policy_id),
-                };
-            },
+        let key = match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, policy_id),
+            None => format!("{}.{}", def_key_prefix.to_string(), policy_id),
         };
+
+        bucket_key(key, bucket)
     }
 
-    pub fn reduce_block(
+    pub fn reduce_block<'b>(
         &mut self,
-        block: &MultiEraBlock,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
         if block.era().has_feature(Feature::MultiAssets) {
 
-            let epoch_no = block_epoch(&self.chain, block);
+            let bucket = self.aggregator.bucket(block);
 
-            for tx in block.txs() {
-                if tx.is_valid() {
+            for tx in ctx.filtered_txs(block) {
+                if tx.is_valid() && filter_matches!(self, block, &tx, ctx) {
                     let mint = tx.mint();
 
                     if let Some(mints) = mint.as_alonzo() {
@@ -64,7 +52,7 @@ impl Reducer {
 
                             let number_of_minted_or_destroyed = assets.len();
 
-                            let key = self.config_key(policy_id, epoch_no);
+                            let key = self.config_key(policy_id, bucket.as_deref());
                             
                             let crdt = model::CRDTCommand::PNCounter(
                                 key,
@@ -82,14 +70,21 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self,
-        chain: &crosscut::ChainWellKnownInfo
-    ) -> super::Reducer {
-        let reducer = Reducer { 
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+        let aggregator = Aggregator::new(self.aggr_by, chain);
+
+        let reducer = Reducer {
             config: self,
-            chain: chain.clone(),
-         };
+            filter,
+            policy: policy.clone(),
+            aggregator,
+        };
 
-        super::Reducer::TxCountByNativeTokenPolicyId(reducer)
+        Ok(super::Reducer::TxCountByNativeTokenPolicyId(reducer))
     }
 }
\ No newline at end of file
@@ -1,20 +1,76 @@
+use pallas::ledger::primitives::babbage::DatumOption;
 use pallas::ledger::traverse::MultiEraOutput;
-use pallas::ledger::traverse::{MultiEraBlock, MultiEraTx, OutputRef};
+use pallas::ledger::traverse::{Asset, MultiEraBlock, MultiEraTx, OutputRef};
 use serde::Deserialize;
+use serde_json::json;
 
+use crate::crosscut::outputs::{babbage_output_bytes, output_parts};
 use crate::{crosscut, model, prelude::*};
 
+#[derive(Deserialize, Clone, Copy)]
+pub enum Projection {
+    /// The original output CBOR
+    Cbor,
+    /// A json document with address, value, datum and script ref
+    Json,
+}
+
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
-    pub filter: Option<Vec<String>>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Store the content of each UTxO under its own `<prefix>.<tx hash>#<idx>`
+    /// key, removed once the output is spent
+    pub projection: Option<Projection>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
 }
 
+/// Renders an output, taking inline datums and script refs from its original
+/// bytes
+fn output_to_json(
+    address: &str,
+    txo: &MultiEraOutput,
+    raw: &[u8],
+) -> Result<serde_json::Value, crate::Error> {
+    let assets: Vec<_> = txo
+        .non_ada_assets()
+        .into_iter()
+        .filter_map(|asset| match asset {
+            Asset::NativeAsset(policy, name, quantity) => Some(json!({
+                "policy": policy.to_string(),
+                "name": hex::encode(name),
+                "quantity": quantity,
+            })),
+            _ => None,
+        })
+        .collect();
+
+    let parts = output_parts(raw).map_err(crate::Error::cbor)?;
+
+    let datum_hash = match txo.datum() {
+        Some(DatumOption::Hash(x)) => Some(x.to_string()),
+        _ => None,
+    };
+
+    let inline_datum = parts.inline_datum.map(hex::encode);
+    let script_ref = parts.script_ref.map(hex::encode);
+
+    Ok(json!({
+        "address": address,
+        "lovelace": txo.lovelace_amount(),
+        "assets": assets,
+        "datum_hash": datum_hash,
+        "inline_datum": inline_datum,
+        "script_ref": script_ref,
+    }))
+}
+
 impl Reducer {
     fn process_consumed_txo(
         &mut self,
@@ -26,47 +82,81 @@ impl Reducer {
 
         let utxo = match utxo {
             Some(x) => x,
-            None => return Ok(())
+            None => return Ok(()),
         };
 
-        let address = utxo.address().map(|x| x.to_string()).or_panic()?;
+        let address = utxo.address().or_panic()?;
 
-        if let Some(addresses) = &self.config.filter {
-            if let Err(_) = addresses.binary_search(&address) {
-                return Ok(());
-            }
+        if !crosscut::filters::in_scope(&self.filter, &address) {
+            return Ok(());
         }
 
+        let address = address.to_string();
+
         let crdt = model::CRDTCommand::set_remove(
             self.config.key_prefix.as_deref(),
             &address,
             input.to_string(),
         );
 
-        output.send(crdt.into())
+        output.send(crdt.into())?;
+
+        if self.config.projection.is_some() {
+            let crdt = model::CRDTCommand::delete(self.config.key_prefix.as_deref(), input);
+            output.send(crdt.into())?;
+        }
+
+        Ok(())
     }
 
     fn process_produced_txo(
         &mut self,
         tx: &MultiEraTx,
         tx_output: &MultiEraOutput,
+        raw: Option<&[u8]>,
         output_idx: usize,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
         let tx_hash = tx.hash();
-        let address = tx_output.address().map(|addr| addr.to_string()).or_panic()?;
+        let address = tx_output.address().or_panic()?;
 
-        if let Some(addresses) = &self.config.filter {
-            if let Err(_) = addresses.binary_search(&address) {
-                return Ok(());
-            }
+        if !crosscut::filters::in_scope(&self.filter, &address) {
+            return Ok(());
         }
 
-        let crdt = model::CRDTCommand::set_add(
-            self.config.key_prefix.as_deref(),
-            &address,
-            format!("{}#{}", tx_hash, output_idx),
-        );
+        let address = address.to_string();
+
+        let utxo_ref = format!("{}#{}", tx_hash, output_idx);
+
+        // outputs of previous eras and collateral returns aren't sliced out of
+        // the tx body, the former have nothing to lose when re-encoded
+        let raw = || match raw {
+            Some(x) => x.to_vec(),
+            None => tx_output.encode(),
+        };
+
+        let value: Option<model::Value> = match self.config.projection {
+            Some(Projection::Cbor) => Some(raw().into()),
+            Some(Projection::Json) => Some(
+                output_to_json(&address, tx_output, &raw())
+                    .or_panic()?
+                    .into(),
+            ),
+            None => None,
+        };
+
+        if let Some(value) = value {
+            let crdt = model::CRDTCommand::any_write_wins(
+                self.config.key_prefix.as_deref(),
+                &utxo_ref,
+                value,
+            );
+
+            output.send(crdt.into())?;
+        }
+
+        let crdt =
+            model::CRDTCommand::set_add(self.config.key_prefix.as_deref(), &address, utxo_ref);
 
         output.send(crdt.into())
     }
@@ -77,13 +167,23 @@ impl Reducer {
         ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        for tx in block.txs().into_iter() {
+        for (idx, tx) in block.txs().into_iter().enumerate() {
+            // spent utxos of in-scope addresses are removed whatever the filter
+            // stage or the tx predicate say, otherwise they would linger in the
+            // set forever
             for consumed in tx.consumes().iter().map(|i| i.output_ref()) {
                 self.process_consumed_txo(&ctx, &consumed, output)?;
             }
 
+            if !ctx.is_retained(idx) || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            let raw_outputs = babbage_output_bytes(&tx).unwrap_or_default();
+
             for (idx, produced) in tx.produces() {
-                self.process_produced_txo(&tx, &produced, idx, output)?;
+                let raw = raw_outputs.get(idx).copied();
+                self.process_produced_txo(&tx, &produced, raw, idx, output)?;
             }
         }
 
@@ -92,12 +192,19 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self, policy: &crosscut::policies::RuntimePolicy) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
         let reducer = Reducer {
             config: self,
+            filter,
             policy: policy.clone(),
         };
 
-        super::Reducer::UtxoByAddress(reducer)
+        Ok(super::Reducer::UtxoByAddress(reducer))
     }
 }
@@ -1,32 +1,22 @@
-use pallas::ledger::addresses::{self, Address, StakeAddress};
 use pallas::ledger::traverse::MultiEraOutput;
 use pallas::ledger::traverse::{MultiEraBlock, MultiEraTx, OutputRef};
 use serde::Deserialize;
 
+use crate::crosscut::addresses::any_address_to_stake_bech32;
 use crate::{crosscut, model, prelude::*};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
-    pub filter: Option<Vec<String>>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
 }
 
-fn any_address_to_stake_bech32(address: Address) -> Option<String> {
-    match address {
-        Address::Shelley(s) => match StakeAddress::try_from(s).ok() {
-            Some(x) => x.to_bech32().ok(),
-            _ => None,
-        },
-        Address::Byron(_) => None,
-        Address::Stake(_) => None,
-    }
-}
-
 impl Reducer {
     fn process_consumed_txo(
         &mut self,
@@ -42,6 +32,11 @@ impl Reducer {
         };
 
         let address = utxo.address().or_panic()?;
+
+        if !crosscut::filters::in_scope(&self.filter, &address) {
+            return Ok(());
+        }
+
         let stake_address = any_address_to_stake_bech32(address);
 
         let stake_address = match stake_address {
@@ -49,12 +44,6 @@ impl Reducer {
             None => return Ok(()),
         };
 
-        if let Some(stake_addresses) = &self.config.filter {
-            if let Err(_) = stake_addresses.binary_search(&stake_address) {
-                return Ok(());
-            }
-        }
-
         let crdt = model::CRDTCommand::set_remove(
             self.config.key_prefix.as_deref(),
             &stake_address,
@@ -73,6 +62,11 @@ impl Reducer {
     ) -> Result<(), gasket::error::Error> {
         let tx_hash = tx.hash();
         let address = tx_output.address().or_panic()?;
+
+        if !crosscut::filters::in_scope(&self.filter, &address) {
+            return Ok(());
+        }
+
         let stake_address = any_address_to_stake_bech32(address);
 
         let stake_address = match stake_address {
@@ -80,12 +74,6 @@ impl Reducer {
             None => return Ok(()),
         };
 
-        if let Some(stake_addresses) = &self.config.filter {
-            if let Err(_) = stake_addresses.binary_search(&stake_address) {
-                return Ok(());
-            }
-        }
-
         let crdt = model::CRDTCommand::set_add(
             self.config.key_prefix.as_deref(),
             &stake_address,
@@ -101,11 +89,17 @@ impl Reducer {
         ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        for tx in block.txs().into_iter() {
+        for (idx, tx) in block.txs().into_iter().enumerate() {
+            // spent utxos are removed whatever the filter stage or the tx
+            // predicate say
             for consumed in tx.consumes().iter().map(|i| i.output_ref()) {
                 self.process_consumed_txo(&ctx, &consumed, output)?;
             }
 
+            if !ctx.is_retained(idx) || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
             for (idx, produced) in tx.produces() {
                 self.process_produced_txo(&tx, &produced, idx, output)?;
             }
@@ -116,28 +110,19 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self, policy: &crosscut::policies::RuntimePolicy) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
         let reducer = Reducer {
             config: self,
+            filter,
             policy: policy.clone(),
         };
 
-        super::Reducer::UtxoByStake(reducer)
-    }
-}
-
-#[cfg(test)]
-mod test {
-    use super::any_address_to_stake_bech32;
-    use pallas::ledger::addresses::Address;
-
-    #[test]
-    fn stake_bech32() {
-        let addr = Address::from_bech32("addr1q86gknmykuldcngv0atyy56ex598p6m8f24nf9nmehmgpgfcmswqs6wnpls37lh7s3du977cxw67a9dpndnmafjs08asyqxe39").unwrap();
-        let stake_bech32 = any_address_to_stake_bech32(addr).unwrap();
-        assert_eq!(
-            stake_bech32,
-            "stake1uyudc8qgd8fslcgl0mlggk7zl0vr8d0wjksekea75eg8n7cw33m0s"
-        );
+        Ok(super::Reducer::UtxoByStake(reducer))
     }
 }
@@ -1,21 +1,23 @@
 use std::str::FromStr;
 
-use gasket::error::AsWorkError;
 use pallas::crypto::hash::Hash;
 use pallas::ledger::traverse::Asset;
 use pallas::ledger::traverse::{MultiEraBlock, MultiEraTx};
 use serde::Deserialize;
 
+use crate::prelude::*;
 use crate::{crosscut, model};
 
 #[derive(Deserialize)]
 pub struct Config {
     pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
     pub policy_ids_hex: Option<Vec<String>>,
 }
 
 pub struct Reducer {
     config: Config,
+    filter: Option<crosscut::filters::Filter>,
     policy: crosscut::policies::RuntimePolicy,
     policy_ids: Option<Vec<Hash<28>>>,
 }
@@ -58,7 +60,7 @@ impl Reducer {
         ctx: &model::BlockContext,
         output: &mut super::OutputPort,
     ) -> Result<(), gasket::error::Error> {
-        for tx in block.txs().into_iter() {
+        for (idx, tx) in block.txs().into_iter().enumerate() {
             for (tx_ref, tx_output) in ctx.find_consumed_txos(&tx, &self.policy).or_panic()? {
                 for asset in tx_output.assets() {
                     if let Asset::NativeAsset(policy, asset, delta) = asset {
@@ -74,6 +76,12 @@ impl Reducer {
                 }
             }
 
+            // spent utxos are removed whatever the filter stage or the tx
+            // predicate say
+            if !ctx.is_retained(idx) || !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
             for (idx, txo) in tx.produces() {
                 for asset in txo.assets() {
                     if let Asset::NativeAsset(policy, asset, delta) = asset {
@@ -95,7 +103,13 @@ impl Reducer {
 }
 
 impl Config {
-    pub fn plugin(self, policy: &crosscut::policies::RuntimePolicy) -> super::Reducer {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
         let policy_ids: Option<Vec<Hash<28>>> = match &self.policy_ids_hex {
             Some(pids) => {
                 let ps = pids
@@ -109,10 +123,11 @@ impl Config {
         };
         let reducer = Reducer {
             config: self,
+            filter,
             policy: policy.clone(),
             policy_ids: policy_ids.clone(),
         };
 
-        super::Reducer::UtxosByAsset(reducer)
+        Ok(super::Reducer::UtxosByAsset(reducer))
     }
 }
@@ -0,0 +1,172 @@
+use pallas::codec::utils::CborWrap;
+use pallas::crypto::hash::{Hash, Hasher};
+use pallas::ledger::primitives::babbage::{DatumOption, PlutusData, ScriptRef, TransactionOutput};
+use pallas::ledger::traverse::{MultiEraBlock, MultiEraOutput, MultiEraTx, OriginalHash};
+use serde::Deserialize;
+
+use crate::crosscut::outputs::{babbage_output_bytes, output_parts, script_ref_body};
+use crate::{crosscut, model, prelude::*};
+
+use super::liquidity_by_token_pair::utils::plutus_data_to_json;
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub key_prefix: Option<String>,
+    pub filter: Option<crosscut::filters::ReducerFilter>,
+
+    /// Also store datums decoded as json (under `<key>.json`)
+    pub include_json: Option<bool>,
+}
+
+pub struct Reducer {
+    config: Config,
+    filter: Option<crosscut::filters::Filter>,
+    policy: crosscut::policies::RuntimePolicy,
+}
+
+impl Reducer {
+    fn config_key(&self, subject: &str) -> String {
+        match &self.config.key_prefix {
+            Some(prefix) => format!("{}.{}", prefix, subject),
+            None => format!("{}.{}", "witness_store".to_string(), subject),
+        }
+    }
+
+    fn send_datum(
+        &mut self,
+        hash: &Hash<32>,
+        cbor: Vec<u8>,
+        data: &PlutusData,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let key = self.config_key(&format!("datum.{}", hash));
+
+        if self.config.include_json.unwrap_or(false) {
+            let crdt = model::CRDTCommand::any_write_wins(
+                None,
+                format!("{}.json", key),
+                plutus_data_to_json(data),
+            );
+
+            output.send(gasket::messaging::Message::from(crdt))?;
+        }
+
+        // datums are immutable by hash, re-writing the same key is idempotent
+        let crdt = model::CRDTCommand::any_write_wins(None, key, cbor);
+
+        output.send(gasket::messaging::Message::from(crdt))
+    }
+
+    fn send_script(
+        &mut self,
+        script: &ScriptRef,
+        cbor: &[u8],
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let hash = match script {
+            ScriptRef::NativeScript(_) => {
+                let body = script_ref_body(cbor)
+                    .map_err(crate::Error::cbor)
+                    .or_panic()?;
+                Hasher::<224>::hash_tagged(body, 0)
+            }
+            ScriptRef::PlutusV1Script(x) => Hasher::<224>::hash_tagged(&x.0, 1),
+            ScriptRef::PlutusV2Script(x) => Hasher::<224>::hash_tagged(&x.0, 2),
+        };
+
+        let crdt = model::CRDTCommand::any_write_wins(
+            None,
+            self.config_key(&format!("script.{}", hash)),
+            cbor.to_vec(),
+        );
+
+        output.send(gasket::messaging::Message::from(crdt))
+    }
+
+    /// Inline datums and script refs are hashed and stored using the bytes of
+    /// the tx body, re-encoding the decoded values could change their hash
+    fn process_txo(
+        &mut self,
+        txo: &MultiEraOutput,
+        raw: &[u8],
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        let parts = output_parts(raw).map_err(crate::Error::cbor).or_panic()?;
+
+        if let (Some(DatumOption::Data(CborWrap(data))), Some(cbor)) =
+            (txo.datum(), parts.inline_datum)
+        {
+            let hash = Hasher::<256>::hash(cbor);
+            self.send_datum(&hash, cbor.to_vec(), &data, output)?;
+        }
+
+        if let Some(TransactionOutput::PostAlonzo(x)) = txo.as_babbage() {
+            if let (Some(CborWrap(script)), Some(cbor)) = (&x.script_ref, parts.script_ref) {
+                self.send_script(script, cbor, output)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn process_witnesses(
+        &mut self,
+        tx: &MultiEraTx,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        for datum in tx.plutus_data() {
+            let hash = datum.original_hash();
+            let cbor = datum.raw_cbor().to_vec();
+            self.send_datum(&hash, cbor, datum, output)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn reduce_block<'b>(
+        &mut self,
+        block: &'b MultiEraBlock<'b>,
+        ctx: &model::BlockContext,
+        output: &mut super::OutputPort,
+    ) -> Result<(), gasket::error::Error> {
+        for tx in ctx.filtered_txs(block).into_iter() {
+            if !filter_matches!(self, block, &tx, ctx) {
+                continue;
+            }
+
+            self.process_witnesses(&tx, output)?;
+
+            // only babbage outputs can hold inline datums or script refs
+            let raw_outputs = match babbage_output_bytes(&tx) {
+                Some(x) => x,
+                None => continue,
+            };
+
+            for (idx, produced) in tx.produces() {
+                if let Some(raw) = raw_outputs.get(idx) {
+                    self.process_txo(&produced, raw, output)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn plugin(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Result<super::Reducer, crate::Error> {
+        let filter = crosscut::filters::compile_optional(&self.filter, chain)?;
+
+        let reducer = Reducer {
+            config: self,
+            filter,
+            policy: policy.clone(),
+        };
+
+        Ok(super::Reducer::WitnessStore(reducer))
+    }
+}
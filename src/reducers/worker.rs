@@ -1,4 +1,5 @@
-use pallas::ledger::traverse::MultiEraBlock;
+use pallas::crypto::hash::Hash;
+use pallas::ledger::traverse::{Era, MultiEraBlock, MultiEraTx};
 
 use crate::{crosscut, model, prelude::*};
 
@@ -54,6 +55,8 @@ impl Worker {
             model::CRDTCommand::block_starting(&block),
         ))?;
 
+        // blocks fully pruned by the filter stage still go through every
+        // reducer, block-level ones don't depend on the retained txs
         for reducer in self.reducers.iter_mut() {
             reducer.reduce_block(&block, ctx, &mut self.output)?;
             self.ops_count.inc(1);
@@ -65,6 +68,34 @@ impl Worker {
 
         Ok(())
     }
+
+    fn reduce_mempool_add(&mut self, era: Era, cbor: &[u8]) -> Result<(), gasket::error::Error> {
+        let tx = MultiEraTx::decode(era, cbor)
+            .map_err(crate::Error::cbor)
+            .apply_policy(&self.policy)
+            .or_panic()?;
+
+        let tx = match tx {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        for reducer in self.reducers.iter_mut() {
+            reducer.reduce_mempool_add(&tx, &mut self.output)?;
+            self.ops_count.inc(1);
+        }
+
+        Ok(())
+    }
+
+    fn reduce_mempool_remove(&mut self, tx_hash: &Hash<32>) -> Result<(), gasket::error::Error> {
+        for reducer in self.reducers.iter_mut() {
+            reducer.reduce_mempool_remove(tx_hash, &mut self.output)?;
+            self.ops_count.inc(1);
+        }
+
+        Ok(())
+    }
 }
 
 impl gasket::runtime::Worker for Worker {
@@ -85,6 +116,12 @@ impl gasket::runtime::Worker for Worker {
             model::EnrichedBlockPayload::RollBack(point) => {
                 log::warn!("rollback requested for {:?}", point);
             }
+            model::EnrichedBlockPayload::MempoolAdd(era, tx) => {
+                self.reduce_mempool_add(era, &tx)?
+            }
+            model::EnrichedBlockPayload::MempoolRemove(hash) => {
+                self.reduce_mempool_remove(&hash)?
+            }
         }
 
         self.input.commit();
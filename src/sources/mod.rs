@@ -6,6 +6,9 @@ use crate::{bootstrap, crosscut, model, storage};
 #[cfg(target_family = "unix")]
 pub mod n2c;
 
+#[cfg(target_family = "unix")]
+pub mod n2c_mempool;
+
 pub mod n2n;
 pub mod utils;
 
@@ -16,6 +19,9 @@ pub enum Config {
 
     #[cfg(target_family = "unix")]
     N2C(n2c::Config),
+
+    #[cfg(target_family = "unix")]
+    N2CMempool(n2c_mempool::Config),
 }
 
 impl Config {
@@ -29,6 +35,7 @@ impl Config {
         match self {
             Config::N2N(c) => Bootstrapper::N2N(c.bootstrapper(chain, intersect, finalize, policy)),
             Config::N2C(c) => Bootstrapper::N2C(c.bootstrapper(chain, intersect, finalize, policy)),
+            Config::N2CMempool(c) => Bootstrapper::N2CMempool(c.bootstrapper(chain, policy)),
         }
     }
 }
@@ -36,6 +43,7 @@ impl Config {
 pub enum Bootstrapper {
     N2N(n2n::Bootstrapper),
     N2C(n2c::Bootstrapper),
+    N2CMempool(n2c_mempool::Bootstrapper),
 }
 
 impl Bootstrapper {
@@ -43,6 +51,7 @@ impl Bootstrapper {
         match self {
             Bootstrapper::N2N(p) => p.borrow_output_port(),
             Bootstrapper::N2C(p) => p.borrow_output_port(),
+            Bootstrapper::N2CMempool(p) => p.borrow_output_port(),
         }
     }
 
@@ -50,6 +59,8 @@ impl Bootstrapper {
         match self {
             Bootstrapper::N2N(p) => p.spawn_stages(pipeline, cursor),
             Bootstrapper::N2C(p) => p.spawn_stages(pipeline, cursor),
+            // the mempool doesn't have a notion of chain points, there's no cursor to follow
+            Bootstrapper::N2CMempool(p) => p.spawn_stages(pipeline),
         }
     }
 }
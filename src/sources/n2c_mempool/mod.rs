@@ -0,0 +1,76 @@
+mod monitor;
+mod transport;
+
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::{bootstrap, crosscut, model};
+
+use gasket::messaging::OutputPort;
+
+/// Streams pending txs from the node's mempool using the local-tx-monitor
+/// mini-protocol
+///
+/// Instead of blocks, this source emits `MempoolAdd` / `MempoolRemove`
+/// payloads. A tx is removed once it leaves the mempool, either because it
+/// landed on-chain or because it was evicted by the node.
+#[derive(Deserialize)]
+pub struct Config {
+    pub path: String,
+
+    /// Amount of milliseconds to wait between mempool snapshots
+    pub poll_interval_ms: Option<u64>,
+}
+
+impl Config {
+    pub fn bootstrapper(
+        self,
+        chain: &crosscut::ChainWellKnownInfo,
+        policy: &crosscut::policies::RuntimePolicy,
+    ) -> Bootstrapper {
+        Bootstrapper {
+            config: self,
+            policy: policy.clone(),
+            chain: chain.clone(),
+            output: Default::default(),
+        }
+    }
+}
+
+pub struct Bootstrapper {
+    config: Config,
+    policy: crosscut::policies::RuntimePolicy,
+    chain: crosscut::ChainWellKnownInfo,
+    output: OutputPort<model::RawBlockPayload>,
+}
+
+impl Bootstrapper {
+    pub fn borrow_output_port(&mut self) -> &'_ mut OutputPort<model::RawBlockPayload> {
+        &mut self.output
+    }
+
+    pub fn spawn_stages(self, pipeline: &mut bootstrap::Pipeline) {
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms.unwrap_or(1000));
+
+        pipeline.register_stage(gasket::runtime::spawn_stage(
+            self::monitor::Worker::new(
+                self.config.path.clone(),
+                poll_interval,
+                self.policy,
+                self.chain,
+                self.output,
+            ),
+            gasket::runtime::Policy {
+                tick_timeout: Some(Duration::from_secs(600)),
+                bootstrap_retry: gasket::retries::Policy {
+                    max_retries: 20,
+                    backoff_factor: 2,
+                    backoff_unit: Duration::from_secs(1),
+                    max_backoff: Duration::from_secs(60),
+                },
+                ..Default::default()
+            },
+            Some("n2c-mempool"),
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use gasket::error::AsWorkError;
+use pallas::crypto::hash::Hash;
+use pallas::ledger::traverse::{Era, MultiEraTx};
+use pallas::network::miniprotocols::txmonitor;
+use pallas::network::multiplexer::StdChannel;
+
+use crate::prelude::*;
+use crate::{crosscut, model, Error};
+
+use super::transport::Transport;
+
+type OutputPort = gasket::messaging::OutputPort<model::RawBlockPayload>;
+
+fn decode_tx(era: u16, cbor: &[u8]) -> Result<(Era, Hash<32>), Error> {
+    let era = Era::try_from(era).map_err(Error::cbor)?;
+    let tx = MultiEraTx::decode(era, cbor).map_err(Error::cbor)?;
+
+    Ok((era, tx.hash()))
+}
+
+pub struct Worker {
+    socket: String,
+    poll_interval: Duration,
+    policy: crosscut::policies::RuntimePolicy,
+    chain: crosscut::ChainWellKnownInfo,
+    monitor: Option<txmonitor::Client<StdChannel>>,
+
+    /// Hashes of the txs present in the last observed mempool snapshot
+    known: HashSet<Hash<32>>,
+
+    output: OutputPort,
+    added_count: gasket::metrics::Counter,
+    removed_count: gasket::metrics::Counter,
+    mempool_size: gasket::metrics::Gauge,
+}
+
+impl Worker {
+    pub fn new(
+        socket: String,
+        poll_interval: Duration,
+        policy: crosscut::policies::RuntimePolicy,
+        chain: crosscut::ChainWellKnownInfo,
+        output: OutputPort,
+    ) -> Self {
+        Self {
+            socket,
+            poll_interval,
+            policy,
+            chain,
+            output,
+            monitor: None,
+            known: HashSet::new(),
+            added_count: Default::default(),
+            removed_count: Default::default(),
+            mempool_size: Default::default(),
+        }
+    }
+
+    fn on_snapshot_tx(
+        &mut self,
+        era: u16,
+        cbor: Vec<u8>,
+        current: &mut HashSet<Hash<32>>,
+    ) -> Result<(), gasket::error::Error> {
+        let decoded = decode_tx(era, &cbor)
+            .apply_policy(&self.policy)
+            .or_panic()?;
+
+        let (era, hash) = match decoded {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+
+        current.insert(hash);
+
+        if !self.known.contains(&hash) {
+            log::debug!("new tx in mempool {}", hash);
+            self.output
+                .send(model::RawBlockPayload::mempool_add(era, cbor))?;
+            self.added_count.inc(1);
+        }
+
+        Ok(())
+    }
+}
+
+impl gasket::runtime::Worker for Worker {
+    fn metrics(&self) -> gasket::metrics::Registry {
+        gasket::metrics::Builder::new()
+            .with_counter("mempool_added", &self.added_count)
+            .with_counter("mempool_removed", &self.removed_count)
+            .with_gauge("mempool_size", &self.mempool_size)
+            .build()
+    }
+
+    fn bootstrap(&mut self) -> Result<(), gasket::error::Error> {
+        let transport = Transport::setup(&self.socket, self.chain.magic).or_retry()?;
+
+        self.monitor = Some(txmonitor::Client::new(transport.channel9));
+
+        Ok(())
+    }
+
+    fn work(&mut self) -> gasket::runtime::WorkResult {
+        let monitor = self.monitor.as_mut().unwrap();
+
+        let slot = monitor.acquire().or_restart()?;
+        log::debug!("acquired mempool snapshot at slot {}", slot);
+
+        let mut snapshot = Vec::new();
+
+        while let Some(txmonitor::EraTx(era, cbor)) = monitor.query_next_tx().or_restart()? {
+            snapshot.push((era, cbor));
+        }
+
+        monitor.release().or_restart()?;
+
+        let mut current = HashSet::with_capacity(snapshot.len());
+
+        for (era, cbor) in snapshot {
+            self.on_snapshot_tx(era, cbor, &mut current)?;
+        }
+
+        // txs that we knew about but are not part of the snapshot anymore either
+        // landed on-chain or were evicted by the node
+        for hash in self.known.difference(&current) {
+            log::debug!("tx left the mempool {}", hash);
+            self.output
+                .send(model::RawBlockPayload::mempool_remove(*hash))?;
+            self.removed_count.inc(1);
+        }
+
+        self.mempool_size.set(current.len() as i64);
+        self.known = current;
+
+        std::thread::sleep(self.poll_interval);
+
+        Ok(gasket::runtime::WorkOutcome::Partial)
+    }
+}
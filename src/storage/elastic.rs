@@ -15,7 +15,7 @@ use crate::{
     bootstrap, crosscut,
     model::{self, CRDTCommand},
     prelude::AppliesPolicy,
-    Error,
+    reducers, Error,
 };
 
 type InputPort = gasket::messaging::TwoPhaseInputPort<model::CRDTCommand>;
@@ -165,12 +165,57 @@ async fn apply_command(cmd: CRDTCommand, client: &Elasticsearch) -> Option<ESRes
             .send()
             .await
             .into(),
+        CRDTCommand::Delete(key) => client
+            .delete(elasticsearch::DeleteParts::IndexId("scrolls", &key))
+            .send()
+            .await
+            .into(),
         CRDTCommand::BlockFinished(_) => {
             log::warn!("Elasticsearch storage doesn't support cursors ATM");
             None
         }
-        _ => todo!(),
+        CRDTCommand::SetAdd(..)
+        | CRDTCommand::SetRemove(..)
+        | CRDTCommand::SortedSetAdd(..)
+        | CRDTCommand::SortedSetRemove(..)
+        | CRDTCommand::TwoPhaseSetAdd(..)
+        | CRDTCommand::TwoPhaseSetRemove(..)
+        | CRDTCommand::GrowOnlySetAdd(..)
+        | CRDTCommand::LastWriteWins(..)
+        | CRDTCommand::PNCounter(..)
+        | CRDTCommand::HashCounter(..)
+        | CRDTCommand::Expire(..)
+        | CRDTCommand::HyperLogLogAdd(..) => {
+            log::warn!("Elasticsearch storage doesn't support {:?}, skipping", cmd);
+            None
+        }
+    }
+}
+
+/// Fails for reducers that emit commands this storage can't apply
+///
+/// Only the commands added along with each reducer are checked, older
+/// reducers relying on sets or counters are still skipped at runtime.
+pub fn check_reducers(reducers: &[reducers::Config]) -> Result<(), Error> {
+    for reducer in reducers {
+        let name = match reducer {
+            reducers::Config::PendingUtxoByAddress(_) => "PendingUtxoByAddress",
+            #[cfg(feature = "unstable")]
+            reducers::Config::BalanceByAddress(_) => "BalanceByAddress",
+            #[cfg(feature = "unstable")]
+            reducers::Config::BalanceByStake(_) => "BalanceByStake",
+            #[cfg(feature = "unstable")]
+            reducers::Config::ChainStats(_) => "ChainStats",
+            _ => continue,
+        };
+
+        return Err(Error::config(format!(
+            "{} reducer can't be used with Elasticsearch storage",
+            name
+        )));
     }
+
+    Ok(())
 }
 
 async fn apply_batch(
@@ -10,7 +10,7 @@ use serde::Deserialize;
 use crate::{
     bootstrap,
     crosscut::{self, PointArg},
-    model,
+    model, reducers,
 };
 
 #[derive(Deserialize)]
@@ -38,6 +38,15 @@ impl Config {
             Config::Elastic(c) => Bootstrapper::Elastic(c.bootstrapper(chain, intersect, policy)),
         }
     }
+
+    /// Fails if any of the reducers emits commands the storage can't apply
+    pub fn check_reducers(&self, reducers: &[reducers::Config]) -> Result<(), crate::Error> {
+        match self {
+            #[cfg(feature = "elastic")]
+            Config::Elastic(_) => elastic::check_reducers(reducers),
+            _ => Ok(()),
+        }
+    }
 }
 
 pub enum Bootstrapper {
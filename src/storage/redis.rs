@@ -21,7 +21,7 @@ impl ToRedisArgs for model::Value {
             model::Value::String(x) => x.write_redis_args(out),
             model::Value::BigInt(x) => x.to_string().write_redis_args(out),
             model::Value::Cbor(x) => x.write_redis_args(out),
-            model::Value::Json(x) => todo!("{}", x),
+            model::Value::Json(x) => x.to_string().write_redis_args(out),
         }
     }
 }
@@ -242,6 +242,47 @@ impl gasket::runtime::Worker for Worker {
                     .incr(key, value)
                     .or_restart()?;
             }
+            model::CRDTCommand::HashCounter(key, field, delta) => {
+                log::debug!(
+                    "increasing hash counter [{}], field [{}], by [{}]",
+                    key,
+                    field,
+                    delta
+                );
+
+                self.connection
+                    .as_mut()
+                    .unwrap()
+                    .hincr(key, field, delta)
+                    .or_restart()?;
+            }
+            model::CRDTCommand::Expire(key, ttl) => {
+                log::debug!("expiring [{}] in [{}] secs", key, ttl);
+
+                self.connection
+                    .as_mut()
+                    .unwrap()
+                    .expire(key, ttl as usize)
+                    .or_restart()?;
+            }
+            model::CRDTCommand::Delete(key) => {
+                log::debug!("deleting [{}]", key);
+
+                self.connection
+                    .as_mut()
+                    .unwrap()
+                    .del(key)
+                    .or_restart()?;
+            }
+            model::CRDTCommand::HyperLogLogAdd(key, member) => {
+                log::debug!("adding to hyperloglog [{}], value [{}]", key, member);
+
+                self.connection
+                    .as_mut()
+                    .unwrap()
+                    .pfadd(key, member)
+                    .or_restart()?;
+            }
             model::CRDTCommand::BlockFinished(point) => {
                 let cursor_str = crosscut::PointArg::from(point).to_string();
 
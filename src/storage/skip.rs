@@ -128,6 +128,23 @@ impl gasket::runtime::Worker for Worker {
             model::CRDTCommand::PNCounter(key, value) => {
                 log::debug!("increasing counter [{}], by [{}]", key, value);
             }
+            model::CRDTCommand::HashCounter(key, field, delta) => {
+                log::debug!(
+                    "increasing hash counter [{}], field [{}], by [{}]",
+                    key,
+                    field,
+                    delta
+                );
+            }
+            model::CRDTCommand::Expire(key, ttl) => {
+                log::debug!("expiring [{}] in [{}] secs", key, ttl);
+            }
+            model::CRDTCommand::Delete(key) => {
+                log::debug!("deleting [{}]", key);
+            }
+            model::CRDTCommand::HyperLogLogAdd(key, member) => {
+                log::debug!("adding to hyperloglog [{}], value [{}]", key, member);
+            }
             model::CRDTCommand::BlockFinished(point) => {
                 log::debug!("block finished {:?}", point);
                 let mut last_point = self.last_point.lock().unwrap();